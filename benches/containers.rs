@@ -0,0 +1,92 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use trie::art_map::ArtMap;
+use trie::byte_map::ByteMap;
+use trie::hex_map::{to_nibbles, HexMap};
+use trie::trie::Trie;
+
+const KEYS: usize = 10_000;
+
+/// Pseudo-random 20 byte keys standing in for SHA-1 digests.
+fn digests() -> Vec<Vec<u8>> {
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    (0..KEYS)
+        .map(|_| {
+            (0..20)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    seed as u8
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn insert(c: &mut Criterion) {
+    let keys = digests();
+    let nibbles: Vec<Vec<u8>> = keys.iter().map(|k| to_nibbles(k)).collect();
+    let mut group = c.benchmark_group("insert");
+    group.bench_function("byte_map", |b| {
+        b.iter(|| {
+            let mut trie: Trie<usize, ByteMap<usize>> = Trie::new(20);
+            for (i, key) in keys.iter().enumerate() {
+                trie.insert(key.clone(), i);
+            }
+            trie
+        })
+    });
+    group.bench_function("art_map", |b| {
+        b.iter(|| {
+            let mut trie: Trie<usize, ArtMap<usize>> = Trie::new(20);
+            for (i, key) in keys.iter().enumerate() {
+                trie.insert(key.clone(), i);
+            }
+            trie
+        })
+    });
+    group.bench_function("hex_map", |b| {
+        b.iter(|| {
+            let mut trie: Trie<usize, HexMap<usize>> = Trie::new(40);
+            for (i, key) in nibbles.iter().enumerate() {
+                trie.insert(key.clone(), i);
+            }
+            trie
+        })
+    });
+    group.finish();
+}
+
+fn get(c: &mut Criterion) {
+    let keys = digests();
+    let bytes: Trie<usize, ByteMap<usize>> = Trie::from_iter(keys.iter().cloned().zip(0..));
+    let hex: Trie<usize, HexMap<usize>> =
+        Trie::from_iter(keys.iter().map(|k| to_nibbles(k)).zip(0..));
+    let art: Trie<usize, ArtMap<usize>> = Trie::from_iter(keys.iter().cloned().zip(0..));
+    let mut group = c.benchmark_group("get");
+    group.bench_function("byte_map", |b| {
+        b.iter(|| {
+            for key in keys.iter() {
+                black_box(bytes.get(key));
+            }
+        })
+    });
+    group.bench_function("art_map", |b| {
+        b.iter(|| {
+            for key in keys.iter() {
+                black_box(art.get(key));
+            }
+        })
+    });
+    group.bench_function("hex_map", |b| {
+        b.iter(|| {
+            for key in keys.iter() {
+                black_box(hex.get(to_nibbles(key)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, insert, get);
+criterion_main!(benches);
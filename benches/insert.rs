@@ -0,0 +1,64 @@
+use bson::oid::ObjectId;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use trie::byte_map::ByteMap;
+use trie::nmap::Nmap;
+use trie::trie::{Container, Trie};
+
+/// Inserts every key into a fresh trie of `key_size` byte keys.
+fn fill<C: Container<u32>>(key_size: usize, keys: Vec<Vec<u8>>) -> Trie<u32, C> {
+    let mut trie = Trie::new(key_size);
+    for (i, key) in keys.into_iter().enumerate() {
+        trie.insert(key, i as u32);
+    }
+    trie
+}
+
+/// The workload of `test_trie` in `lib.rs`: 10M ObjectIds, which arrive in
+/// increasing order and mostly take the append fast path.
+fn object_ids(c: &mut Criterion) {
+    let keys: Vec<Vec<u8>> = (0..10_000_000)
+        .map(|_| ObjectId::new().bytes().to_vec())
+        .collect();
+    let mut group = c.benchmark_group("insert");
+    group.sample_size(10);
+    group.bench_function("object_id_10m", |b| {
+        b.iter_batched(
+            || keys.clone(),
+            |keys| fill::<ByteMap<u32>>(12, keys),
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+/// Keys in scattered order, each searching the leaf chain for its successor
+/// through the containers' `next`.
+fn scattered(c: &mut Criterion) {
+    let order = |i: u64| i.wrapping_mul(2654435761);
+    let bytes: Vec<Vec<u8>> = (0..1_000_000u64)
+        .map(|i| (order(i) as u32).to_be_bytes()[1..].to_vec())
+        .collect();
+    let digits: Vec<Vec<u8>> = (0..1_000_000u64)
+        .map(|i| format!("{:08}", order(i) % 100_000_000).into_bytes())
+        .collect();
+    let mut group = c.benchmark_group("insert");
+    group.sample_size(10);
+    group.bench_function("byte_map_scattered_1m", |b| {
+        b.iter_batched(
+            || bytes.clone(),
+            |keys| fill::<ByteMap<u32>>(3, keys),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("nmap_scattered_1m", |b| {
+        b.iter_batched(
+            || digits.clone(),
+            |keys| fill::<Nmap<u32>>(8, keys),
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, object_ids, scattered);
+criterion_main!(benches);
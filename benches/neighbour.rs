@@ -0,0 +1,72 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use trie::byte_map::ByteMap;
+use trie::nmap::Nmap;
+use trie::trie::{Container, Trie};
+
+const KEYS: u64 = 100_000;
+
+fn order(i: u64) -> u64 {
+    i.wrapping_mul(2654435761)
+}
+
+/// A trie holding the even entries of `keys`; the odd ones are the probes,
+/// which miss and have to find their neighbours through the containers'
+/// `prev` and `next`.
+fn split<C: Container<u32>>(key_size: usize, keys: Vec<Vec<u8>>) -> (Trie<u32, C>, Vec<Vec<u8>>) {
+    let mut trie = Trie::new(key_size);
+    let mut probes = vec![];
+    for (i, key) in keys.into_iter().enumerate() {
+        if i % 2 == 0 {
+            trie.insert(key, i as u32);
+        } else {
+            probes.push(key);
+        }
+    }
+    (trie, probes)
+}
+
+fn query<C: Container<u32>>(
+    c: &mut Criterion,
+    name: &str,
+    trie: &Trie<u32, C>,
+    probes: &[Vec<u8>],
+) {
+    let mut group = c.benchmark_group("neighbour");
+    group.bench_function(format!("{}_gt", name), |b| {
+        b.iter(|| {
+            for key in probes {
+                black_box(trie.gt(key));
+            }
+        })
+    });
+    group.bench_function(format!("{}_lt", name), |b| {
+        b.iter(|| {
+            for key in probes {
+                black_box(trie.lt(key));
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Sparse 3 byte keys, so the containers near the leaves hold only a few of
+/// their 256 buckets.
+fn byte_map(c: &mut Criterion) {
+    let keys = (0..KEYS)
+        .map(|i| (order(i) as u32).to_be_bytes()[1..].to_vec())
+        .collect();
+    let (trie, probes) = split::<ByteMap<u32>>(3, keys);
+    query(c, "byte_map", &trie, &probes);
+}
+
+/// Sparse 8 digit keys, for Nmap's 10 buckets.
+fn nmap(c: &mut Criterion) {
+    let keys = (0..KEYS)
+        .map(|i| format!("{:08}", order(i) % 100_000_000).into_bytes())
+        .collect();
+    let (trie, probes) = split::<Nmap<u32>>(8, keys);
+    query(c, "nmap", &trie, &probes);
+}
+
+criterion_group!(benches, byte_map, nmap);
+criterion_main!(benches);
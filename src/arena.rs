@@ -0,0 +1,218 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::num::NonZeroU32;
+use std::ops::{Index, IndexMut};
+
+/// A handle to a value stored in an `Arena`.
+///
+/// It is four bytes, and so is an `Option<Id<V>>`, so containers full of
+/// handles take half the room of pointers.
+pub struct Id<V> {
+    index: NonZeroU32,
+    marker: PhantomData<fn() -> V>,
+}
+
+impl<V> Id<V> {
+    fn new(index: usize) -> Id<V> {
+        let index = u32::try_from(index + 1)
+            .ok()
+            .and_then(NonZeroU32::new)
+            .expect("arena is full");
+        Id {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    /// The slot of the value in its arena.
+    pub fn index(self) -> usize {
+        self.index.get() as usize - 1
+    }
+}
+
+impl<V> Clone for Id<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for Id<V> {}
+
+impl<V> PartialEq for Id<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<V> Eq for Id<V> {}
+
+impl<V> fmt::Debug for Id<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Id({})", self.index())
+    }
+}
+
+enum Slot<V> {
+    Occupied(V),
+    /// A free slot, linking to the next free one.
+    Vacant(Option<Id<V>>),
+}
+
+/// A slab of values addressed by `Id`.
+///
+/// Values live side by side in one buffer, a removed value's slot is reused
+/// by a later insert, and `clear` or dropping the arena releases everything
+/// at once.
+pub struct Arena<V> {
+    slots: Vec<Slot<V>>,
+    free: Option<Id<V>>,
+    len: usize,
+}
+
+impl<V> Default for Arena<V> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+impl<V> fmt::Debug for Arena<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("len", &self.len)
+            .field("slots", &self.slots.len())
+            .finish()
+    }
+}
+
+impl<V> Arena<V> {
+    pub fn new() -> Arena<V> {
+        Arena {
+            slots: Vec::new(),
+            free: None,
+            len: 0,
+        }
+    }
+
+    /// Stores `val` in a free slot and returns its handle.
+    pub fn insert(&mut self, val: V) -> Id<V> {
+        self.len += 1;
+        match self.free {
+            Some(id) => {
+                let slot = mem::replace(&mut self.slots[id.index()], Slot::Occupied(val));
+                self.free = match slot {
+                    Slot::Vacant(next) => next,
+                    Slot::Occupied(_) => unreachable!("free list points at a value"),
+                };
+                id
+            }
+            None => {
+                self.slots.push(Slot::Occupied(val));
+                Id::new(self.slots.len() - 1)
+            }
+        }
+    }
+
+    /// Takes the value out of the arena, freeing its slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was already removed.
+    pub fn remove(&mut self, id: Id<V>) -> V {
+        if self.get(id).is_none() {
+            panic!("{:?} was already removed", id);
+        }
+        match mem::replace(&mut self.slots[id.index()], Slot::Vacant(self.free)) {
+            Slot::Occupied(val) => {
+                self.free = Some(id);
+                self.len -= 1;
+                val
+            }
+            Slot::Vacant(_) => unreachable!("checked above"),
+        }
+    }
+
+    pub fn get(&self, id: Id<V>) -> Option<&V> {
+        match self.slots.get(id.index())? {
+            Slot::Occupied(val) => Some(val),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, id: Id<V>) -> Option<&mut V> {
+        match self.slots.get_mut(id.index())? {
+            Slot::Occupied(val) => Some(val),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    /// A pointer to the value of `id`. Unlike `get` it does not borrow the
+    /// rest of the buffer, so it may be read while `&mut` references into
+    /// other slots are alive.
+    pub(crate) fn get_ptr(&self, id: Id<V>) -> Option<*const V> {
+        if id.index() >= self.slots.len() {
+            return None;
+        }
+        // `as_ptr` does not create a reference to the whole buffer.
+        let slot = unsafe { &*self.slots.as_ptr().add(id.index()) };
+        match slot {
+            Slot::Occupied(val) => Some(val),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    /// A mutable pointer to the value of `id`. Unlike `get_mut` it does not
+    /// borrow the rest of the buffer, so pointers to distinct values may be
+    /// used at the same time.
+    pub(crate) fn get_mut_ptr(&mut self, id: Id<V>) -> Option<*mut V> {
+        if id.index() >= self.slots.len() {
+            return None;
+        }
+        // `as_mut_ptr` does not create a reference to the whole buffer.
+        let slot = unsafe { &mut *self.slots.as_mut_ptr().add(id.index()) };
+        match slot {
+            Slot::Occupied(val) => Some(val),
+            Slot::Vacant(_) => None,
+        }
+    }
+
+    /// The number of values stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of slots in use or free, which is what the buffer holds.
+    pub fn slots(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The bytes of the buffer, which holds a value or a free link per slot.
+    pub fn heap_bytes(&self) -> usize {
+        self.slots.capacity() * mem::size_of::<Slot<V>>()
+    }
+
+    /// Drops every value, keeping the buffer for reuse.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.free = None;
+        self.len = 0;
+    }
+}
+
+impl<V> Index<Id<V>> for Arena<V> {
+    type Output = V;
+
+    fn index(&self, id: Id<V>) -> &V {
+        self.get(id).expect("no value for id")
+    }
+}
+
+impl<V> IndexMut<Id<V>> for Arena<V> {
+    fn index_mut(&mut self, id: Id<V>) -> &mut V {
+        self.get_mut(id).expect("no value for id")
+    }
+}
@@ -0,0 +1,397 @@
+use crate::trie::{Container, Link, NodeId};
+use std::fmt::Debug;
+use std::mem;
+
+/// A container that adapts its layout to the number of children, after the
+/// Adaptive Radix Tree: up to 4 and 16 children are kept in sorted key
+/// arrays, up to 48 behind a 256 byte index, and beyond that in a full 256
+/// slot array like `ByteMap`. Sparse nodes, the bulk of a trie over random
+/// keys, thus take a few dozen bytes instead of 2 KiB.
+#[derive(Debug)]
+pub struct ArtMap<T> {
+    node: Node<T>,
+}
+
+#[derive(Debug)]
+enum Node<T> {
+    N4(Sorted<T, 4>),
+    N16(Box<Sorted<T, 16>>),
+    N48(Box<Indexed<T>>),
+    N256(Box<Full<T>>),
+}
+
+/// Children in key order, `keys[..len]` naming `children[..len]`.
+#[derive(Debug)]
+struct Sorted<T, const N: usize> {
+    len: usize,
+    keys: [u8; N],
+    children: [Link<T, ArtMap<T>>; N],
+}
+
+/// Up to 48 children in arbitrary slots, `index[k]` holding one plus the
+/// slot of key `k` or zero when absent.
+#[derive(Debug)]
+struct Indexed<T> {
+    len: usize,
+    index: [u8; 256],
+    children: [Link<T, ArtMap<T>>; 48],
+}
+
+/// A child slot for every key, counting the occupied ones.
+#[derive(Debug)]
+struct Full<T> {
+    len: usize,
+    children: [Link<T, ArtMap<T>>; 256],
+}
+
+// shrink only well below the capacity of the smaller layout, so a node
+// hovering around a boundary does not convert on every set and del.
+const SHRINK_256: usize = 40;
+const SHRINK_48: usize = 12;
+const SHRINK_16: usize = 3;
+
+impl<T, const N: usize> Sorted<T, N> {
+    fn new() -> Self {
+        Sorted {
+            len: 0,
+            keys: [0; N],
+            children: [None; N],
+        }
+    }
+
+    /// The position of `k`, or where it would be inserted.
+    fn search(&self, k: u8) -> Result<usize, usize> {
+        self.keys[..self.len].binary_search(&k)
+    }
+
+    fn get(&self, k: u8) -> Link<T, ArtMap<T>> {
+        let i = self.search(k).ok()?;
+        self.children[i]
+    }
+
+    /// Inserts a new key, which must be absent, into a node with room.
+    fn insert(&mut self, k: u8, v: NodeId<T, ArtMap<T>>) {
+        let i = self.search(k).unwrap_or_else(|i| i);
+        self.keys.copy_within(i..self.len, i + 1);
+        self.children.copy_within(i..self.len, i + 1);
+        self.keys[i] = k;
+        self.children[i] = Some(v);
+        self.len += 1;
+    }
+
+    fn remove(&mut self, k: u8) -> bool {
+        let i = match self.search(k) {
+            Ok(i) => i,
+            Err(_) => return false,
+        };
+        self.keys.copy_within(i + 1..self.len, i);
+        self.children.copy_within(i + 1..self.len, i);
+        self.len -= 1;
+        self.children[self.len] = None;
+        true
+    }
+
+    fn prev(&self, k: u8) -> Link<T, ArtMap<T>> {
+        let i = self.search(k).unwrap_or_else(|i| i);
+        if i == 0 {
+            return None;
+        }
+        self.children[i - 1]
+    }
+
+    fn next(&self, k: u8) -> Link<T, ArtMap<T>> {
+        let i = match self.search(k) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+        if i >= self.len {
+            return None;
+        }
+        self.children[i]
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (u8, NodeId<T, ArtMap<T>>)> + '_ {
+        (0..self.len).filter_map(move |i| Some((self.keys[i], self.children[i]?)))
+    }
+}
+
+impl<T> Indexed<T> {
+    fn new() -> Self {
+        Indexed {
+            len: 0,
+            index: [0; 256],
+            children: [None; 48],
+        }
+    }
+
+    fn get(&self, k: u8) -> Link<T, ArtMap<T>> {
+        match self.index[k as usize] {
+            0 => None,
+            slot => self.children[slot as usize - 1],
+        }
+    }
+
+    fn insert(&mut self, k: u8, v: NodeId<T, ArtMap<T>>) {
+        if let Some(slot) = self.children.iter().position(|c| c.is_none()) {
+            self.children[slot] = Some(v);
+            self.index[k as usize] = slot as u8 + 1;
+            self.len += 1;
+        }
+    }
+
+    fn remove(&mut self, k: u8) -> bool {
+        match self.index[k as usize] {
+            0 => false,
+            slot => {
+                self.children[slot as usize - 1] = None;
+                self.index[k as usize] = 0;
+                self.len -= 1;
+                true
+            }
+        }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (u8, NodeId<T, ArtMap<T>>)> + '_ {
+        (0..=255u8).filter_map(move |k| Some((k, self.get(k)?)))
+    }
+}
+
+impl<T> Full<T> {
+    fn new() -> Self {
+        Full {
+            len: 0,
+            children: [None; 256],
+        }
+    }
+
+    fn insert(&mut self, k: u8, v: NodeId<T, ArtMap<T>>) {
+        if self.children[k as usize].replace(v).is_none() {
+            self.len += 1;
+        }
+    }
+
+    fn remove(&mut self, k: u8) -> bool {
+        let found = self.children[k as usize].take().is_some();
+        if found {
+            self.len -= 1;
+        }
+        found
+    }
+}
+
+impl<T> ArtMap<T> {
+    /// The number of children.
+    pub fn len(&self) -> usize {
+        match &self.node {
+            Node::N4(n) => n.len,
+            Node::N16(n) => n.len,
+            Node::N48(n) => n.len,
+            Node::N256(n) => n.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every child with its key, in key order.
+    fn entries(&self) -> Vec<(u8, NodeId<T, Self>)> {
+        match &self.node {
+            Node::N4(n) => n.entries().collect(),
+            Node::N16(n) => n.entries().collect(),
+            Node::N48(n) => n.entries().collect(),
+            Node::N256(n) => (0..=255u8)
+                .filter_map(|k| Some((k, n.children[k as usize]?)))
+                .collect(),
+        }
+    }
+
+    /// The occupied slot holding key `k`.
+    fn slot_mut(&mut self, k: u8) -> Option<&mut Link<T, Self>> {
+        let slot = match &mut self.node {
+            Node::N4(n) => &mut n.children[n.search(k).ok()?],
+            Node::N16(n) => &mut n.children[n.search(k).ok()?],
+            Node::N48(n) => match n.index[k as usize] {
+                0 => return None,
+                i => &mut n.children[i as usize - 1],
+            },
+            Node::N256(n) => &mut n.children[k as usize],
+        };
+        slot.as_ref()?;
+        Some(slot)
+    }
+
+    /// Moves the children into the next larger layout.
+    fn grow(&mut self) {
+        let entries = self.entries();
+        self.node = match &self.node {
+            Node::N4(_) => {
+                let mut n = Box::new(Sorted::new());
+                entries.into_iter().for_each(|(k, v)| n.insert(k, v));
+                Node::N16(n)
+            }
+            Node::N16(_) => {
+                let mut n = Box::new(Indexed::new());
+                entries.into_iter().for_each(|(k, v)| n.insert(k, v));
+                Node::N48(n)
+            }
+            Node::N48(_) | Node::N256(_) => {
+                let mut n = Box::new(Full::new());
+                entries.into_iter().for_each(|(k, v)| n.insert(k, v));
+                Node::N256(n)
+            }
+        };
+    }
+
+    /// Moves the children into the next smaller layout once few enough are
+    /// left.
+    fn shrink(&mut self) {
+        let len = self.len();
+        let node = match &self.node {
+            Node::N16(_) if len <= SHRINK_16 => {
+                let mut n = Sorted::new();
+                self.entries().into_iter().for_each(|(k, v)| n.insert(k, v));
+                Node::N4(n)
+            }
+            Node::N48(_) if len <= SHRINK_48 => {
+                let mut n = Box::new(Sorted::new());
+                self.entries().into_iter().for_each(|(k, v)| n.insert(k, v));
+                Node::N16(n)
+            }
+            Node::N256(_) if len <= SHRINK_256 => {
+                let mut n = Box::new(Indexed::new());
+                self.entries().into_iter().for_each(|(k, v)| n.insert(k, v));
+                Node::N48(n)
+            }
+            _ => return,
+        };
+        self.node = node;
+    }
+}
+
+impl<T> std::fmt::Display for ArtMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut msg = String::from("[");
+        for (k, node) in self.entries() {
+            msg = format!("{},{}:{},", msg, k, node.index());
+        }
+        write!(f, "{}]", msg)?;
+        Ok(())
+    }
+}
+
+impl<T> Container<T> for ArtMap<T> {
+    fn new() -> ArtMap<T> {
+        ArtMap {
+            node: Node::N4(Sorted::new()),
+        }
+    }
+    fn get(&self, k: u8) -> Link<T, Self> {
+        match &self.node {
+            Node::N4(n) => n.get(k),
+            Node::N16(n) => n.get(k),
+            Node::N48(n) => n.get(k),
+            Node::N256(n) => n.children[k as usize],
+        }
+    }
+
+    fn set(&mut self, k: u8, v: NodeId<T, Self>) {
+        if let Some(slot) = self.slot_mut(k) {
+            *slot = Some(v);
+            return;
+        }
+        if self.len() == self.capacity() {
+            self.grow();
+        }
+        match &mut self.node {
+            Node::N4(n) => n.insert(k, v),
+            Node::N16(n) => n.insert(k, v),
+            Node::N48(n) => n.insert(k, v),
+            Node::N256(n) => n.insert(k, v),
+        }
+    }
+    fn del(&mut self, k: u8) -> bool {
+        let found = match &mut self.node {
+            Node::N4(n) => n.remove(k),
+            Node::N16(n) => n.remove(k),
+            Node::N48(n) => n.remove(k),
+            Node::N256(n) => n.remove(k),
+        };
+        if found {
+            self.shrink();
+        }
+        found
+    }
+    fn prev(&self, k: u8) -> Link<T, Self> {
+        match &self.node {
+            Node::N4(n) => n.prev(k),
+            Node::N16(n) => n.prev(k),
+            Node::N48(n) => (0..k).rev().find_map(|i| n.get(i)),
+            Node::N256(n) => n.children[..k as usize].iter().rev().find_map(|c| *c),
+        }
+    }
+
+    fn next(&self, k: u8) -> Link<T, Self> {
+        match &self.node {
+            Node::N4(n) => n.next(k),
+            Node::N16(n) => n.next(k),
+            Node::N48(n) => (k as usize + 1..256).find_map(|i| n.get(i as u8)),
+            Node::N256(n) => n.children[k as usize + 1..].iter().find_map(|c| *c),
+        }
+    }
+    fn is_head(&self, k: u8) -> bool {
+        // handles are unique, so the child of `k` is the head only if `k` is.
+        self.get(k).is_some_and(|node| self.head() == Some(node))
+    }
+    fn head(&self) -> Link<T, Self> {
+        match &self.node {
+            Node::N4(n) => n.children[0],
+            Node::N16(n) => n.children[0],
+            Node::N48(n) => (0..=255u8).find_map(|i| n.get(i)),
+            Node::N256(n) => n.children.iter().find_map(|c| *c),
+        }
+    }
+
+    fn is_tail(&self, k: u8) -> bool {
+        self.get(k).is_some_and(|node| self.tail() == Some(node))
+    }
+
+    fn tail(&self) -> Link<T, Self> {
+        match &self.node {
+            Node::N4(n) => n.children[..n.len].last().copied().flatten(),
+            Node::N16(n) => n.children[..n.len].last().copied().flatten(),
+            Node::N48(n) => (0..=255u8).rev().find_map(|i| n.get(i)),
+            Node::N256(n) => n.children.iter().rev().find_map(|c| *c),
+        }
+    }
+    fn keys(&self) -> Vec<u8> {
+        self.entries().into_iter().map(|(k, _)| k).collect()
+    }
+
+    fn pad() -> u8 {
+        0
+    }
+
+    fn valid(_k: u8) -> bool {
+        true
+    }
+
+    /// The number of child slots of the current layout: 4, 16, 48 or 256.
+    fn capacity(&self) -> usize {
+        match &self.node {
+            Node::N4(_) => 4,
+            Node::N16(_) => 16,
+            Node::N48(_) => 48,
+            Node::N256(_) => 256,
+        }
+    }
+
+    fn heap_bytes(&self) -> usize {
+        match &self.node {
+            Node::N4(_) => 0,
+            Node::N16(_) => mem::size_of::<Sorted<T, 16>>(),
+            Node::N48(_) => mem::size_of::<Indexed<T>>(),
+            Node::N256(_) => mem::size_of::<Full<T>>(),
+        }
+    }
+}
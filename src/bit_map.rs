@@ -0,0 +1,103 @@
+use crate::trie::{Container, Link, NodeId};
+use std::fmt::Debug;
+
+/// A two way container for keys spelled one bit per byte, `0` or `1`.
+#[derive(Debug)]
+pub struct BitMap<T> {
+    buckets: [Link<T, BitMap<T>>; 2],
+}
+
+impl<T> std::fmt::Display for BitMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut msg = String::from("[");
+        for i in 0..2 {
+            if let Some(node) = self.buckets[i] {
+                msg = format!("{},{},", msg, node.index());
+            } else {
+                msg = format!("{},{},", msg, "None");
+            }
+        }
+        write!(f, "{}]", msg)?;
+        Ok(())
+    }
+}
+
+impl<T> Container<T> for BitMap<T> {
+    fn new() -> BitMap<T> {
+        BitMap {
+            buckets: [None, None],
+        }
+    }
+    fn get(&self, k: u8) -> Link<T, Self> {
+        *self.buckets.get(k as usize)?
+    }
+
+    fn set(&mut self, k: u8, v: NodeId<T, Self>) {
+        if let Some(bucket) = self.buckets.get_mut(k as usize) {
+            *bucket = Some(v);
+        }
+    }
+    fn del(&mut self, k: u8) -> bool {
+        match self.buckets.get_mut(k as usize) {
+            Some(bucket) => bucket.take().is_some(),
+            None => false,
+        }
+    }
+    fn prev(&self, k: u8) -> Link<T, Self> {
+        match k {
+            0 => None,
+            1 => self.buckets[0],
+            _ => self.tail(),
+        }
+    }
+
+    fn next(&self, k: u8) -> Link<T, Self> {
+        match k {
+            0 => self.buckets[1],
+            _ => None,
+        }
+    }
+    fn is_head(&self, k: u8) -> bool {
+        match k {
+            0 => self.buckets[0].is_some(),
+            1 => self.buckets[0].is_none() && self.buckets[1].is_some(),
+            _ => false,
+        }
+    }
+    fn head(&self) -> Link<T, Self> {
+        self.buckets[0].or(self.buckets[1])
+    }
+
+    fn is_tail(&self, k: u8) -> bool {
+        match k {
+            0 => self.buckets[1].is_none() && self.buckets[0].is_some(),
+            1 => self.buckets[1].is_some(),
+            _ => false,
+        }
+    }
+
+    fn tail(&self) -> Link<T, Self> {
+        self.buckets[1].or(self.buckets[0])
+    }
+    fn keys(&self) -> Vec<u8> {
+        let mut keys = Vec::with_capacity(2);
+        for i in 0..2 {
+            if self.buckets[i].is_some() {
+                keys.push(i as u8)
+            }
+        }
+        keys
+    }
+
+    fn pad() -> u8 {
+        0
+    }
+
+    fn valid(k: u8) -> bool {
+        k < 2
+    }
+
+    fn capacity(&self) -> usize {
+        2
+    }
+}
@@ -1,10 +1,11 @@
-use crate::{trie::Container, trie::TrieNode};
+use crate::trie::{Container, Link, NodeId};
 use std::fmt::Debug;
-use std::ptr::NonNull;
 
 #[derive(Debug)]
 pub struct ByteMap<T> {
-    buckets: [Option<NonNull<TrieNode<T>>>; 256],
+    buckets: [Link<T, ByteMap<T>>; 256],
+    /// Bit `k` is set when `buckets[k]` holds a node.
+    bits: [u64; 4],
 }
 
 impl<T> std::fmt::Display for ByteMap<T> {
@@ -12,7 +13,7 @@ impl<T> std::fmt::Display for ByteMap<T> {
         let mut msg = String::from("[");
         for i in 0..10 {
             if let Some(node) = self.buckets[i] {
-                msg = format!("{},{},", msg, node.as_ptr() as u8);
+                msg = format!("{},{},", msg, node.index());
             } else {
                 msg = format!("{},{},", msg, "None");
             }
@@ -22,6 +23,46 @@ impl<T> std::fmt::Display for ByteMap<T> {
     }
 }
 
+impl<T> ByteMap<T> {
+    /// The smallest occupied key at or after `from`.
+    fn first_from(&self, from: usize) -> Option<usize> {
+        let mut w = from / 64;
+        if w >= 4 {
+            return None;
+        }
+        let mut word = self.bits[w] & (u64::MAX << (from % 64));
+        loop {
+            if word != 0 {
+                return Some(w * 64 + word.trailing_zeros() as usize);
+            }
+            w += 1;
+            if w == 4 {
+                return None;
+            }
+            word = self.bits[w];
+        }
+    }
+
+    /// The largest occupied key before `to`.
+    fn last_before(&self, to: usize) -> Option<usize> {
+        if to == 0 {
+            return None;
+        }
+        let mut w = (to - 1) / 64;
+        let mut word = self.bits[w] & (u64::MAX >> (63 - (to - 1) % 64));
+        loop {
+            if word != 0 {
+                return Some(w * 64 + 63 - word.leading_zeros() as usize);
+            }
+            if w == 0 {
+                return None;
+            }
+            w -= 1;
+            word = self.bits[w];
+        }
+    }
+}
+
 impl<T> Container<T> for ByteMap<T> {
     fn new() -> ByteMap<T> {
         ByteMap {
@@ -46,115 +87,61 @@ impl<T> Container<T> for ByteMap<T> {
                 None, None, None, None, None, None, None, None, None, None, None, None, None, None,
                 None, None, None, None,
             ],
+            bits: [0; 4],
         }
     }
-    fn get(&self, k: u8) -> Option<NonNull<TrieNode<T>>> {
+    fn get(&self, k: u8) -> Link<T, Self> {
         self.buckets[k as usize]
     }
 
-    fn set(&mut self, k: u8, v: NonNull<TrieNode<T>>) {
-        unsafe {
-            if let Some(prev) = self.prev(k) {
-                (*prev.as_ptr()).next = Some(v);
-                (*v.as_ptr()).prev = Some(prev);
-            }
-            if let Some(next) = self.next(k) {
-                (*next.as_ptr()).prev = Some(v);
-                (*v.as_ptr()).next = Some(next);
-            }
-            self.buckets[k as usize] = Some(v);
-        }
-        ()
+    fn set(&mut self, k: u8, v: NodeId<T, Self>) {
+        self.buckets[k as usize] = Some(v);
+        self.bits[k as usize / 64] |= 1 << (k % 64);
     }
     fn del(&mut self, k: u8) -> bool {
-        unsafe {
-            if let Some(node) = self.buckets[k as usize] {
-                let prev = (*node.as_ptr()).prev;
-                let next = (*node.as_ptr()).next;
-                if let Some(p) = prev {
-                    (*p.as_ptr()).next = next
-                }
-                if let Some(n) = (*node.as_ptr()).next {
-                    (*n.as_ptr()).prev = prev
-                }
-                self.buckets[k as usize] = None;
-                return true;
-            }
-        }
-        false
+        self.bits[k as usize / 64] &= !(1 << (k % 64));
+        self.buckets[k as usize].take().is_some()
     }
-    fn prev(&self, k: u8) -> Option<NonNull<TrieNode<T>>> {
-        if k == 0 {
-            return None;
-        }
-        let k = k as usize - 1;
-        for i in k..256 {
-            if !self.buckets[255 - i].is_none() {
-                return self.buckets[255 - i];
-            }
-        }
-        None
+    fn prev(&self, k: u8) -> Link<T, Self> {
+        self.buckets[self.last_before(k as usize)?]
     }
 
-    fn next(&self, k: u8) -> Option<NonNull<TrieNode<T>>> {
-        if k == 255 {
-            return None;
-        }
-        let k = (k + 1) as usize;
-        for i in k..256 {
-            if !self.buckets[i].is_none() {
-                return self.buckets[i];
-            }
-        }
-        None
+    fn next(&self, k: u8) -> Link<T, Self> {
+        self.buckets[self.first_from(k as usize + 1)?]
     }
     fn is_head(&self, k: u8) -> bool {
-        let k = k as usize;
-        for i in 0..256 {
-            if !self.buckets[i].is_none() {
-                return i == k;
-            }
-        }
-        false
+        self.first_from(0) == Some(k as usize)
     }
-    fn head(&self) -> Option<NonNull<TrieNode<T>>> {
-        for i in 0..256 {
-            if !self.buckets[i].is_none() {
-                return self.buckets[i];
-            }
-        }
-        None
+    fn head(&self) -> Link<T, Self> {
+        self.buckets[self.first_from(0)?]
     }
 
     fn is_tail(&self, k: u8) -> bool {
-        let k = k as usize;
-        for i in 0..256 {
-            if !self.buckets[255 - i].is_none() {
-                return i == k;
-            }
-        }
-        false
+        self.last_before(256) == Some(k as usize)
     }
 
-    fn tail(&self) -> Option<NonNull<TrieNode<T>>> {
-        for i in 0..256 {
-            if !self.buckets[255 - i].is_none() {
-                return self.buckets[i];
-            }
-        }
-        None
+    fn tail(&self) -> Link<T, Self> {
+        self.buckets[self.last_before(256)?]
     }
     fn keys(&self) -> Vec<u8> {
         let mut keys = Vec::with_capacity(10);
-        for i in 0..256 {
-            if let Some(_) = self.buckets[i] {
-                keys.push(i as u8)
-            }
+        let mut i = self.first_from(0);
+        while let Some(k) = i {
+            keys.push(k as u8);
+            i = self.first_from(k + 1);
         }
-        return keys;
+        keys
     }
 
     fn pad() -> u8 {
-        return 0;
+        0
+    }
+
+    fn valid(_k: u8) -> bool {
+        true
+    }
+
+    fn capacity(&self) -> usize {
+        256
     }
 }
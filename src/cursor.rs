@@ -0,0 +1,166 @@
+use crate::error::{ErrorKind, TrieError};
+use crate::trie::{Container, Link, Trie};
+
+/// A position in the leaf chain of a `Trie`, stepping through the entries
+/// in key order.
+///
+/// Besides the entries, a cursor can rest on a "ghost" position past the
+/// last entry and before the first one, where `key` and `value` are `None`.
+/// Moving forward from the ghost goes to the first entry, backward to the
+/// last one.
+pub struct Cursor<'a, T: 'a, C: 'a> {
+    pub(crate) trie: &'a Trie<T, C>,
+    pub(crate) current: Link<T, C>,
+}
+
+impl<T, C> Clone for Cursor<'_, T, C> {
+    fn clone(&self) -> Self {
+        Cursor { ..*self }
+    }
+}
+
+/// A `Cursor` that can also change the value under it, remove the current
+/// entry and insert next to it.
+pub struct CursorMut<'a, T: 'a, C: 'a> {
+    pub(crate) trie: &'a mut Trie<T, C>,
+    pub(crate) current: Link<T, C>,
+}
+
+impl<'a, T, C> Cursor<'a, T, C>
+where
+    C: Container<T>,
+{
+    /// Moves to the first entry whose key is greater than or equal to `key`,
+    /// or to the ghost position if there is none.
+    pub fn seek(&mut self, key: impl AsRef<[u8]>) {
+        let key = self.trie.probe(key.as_ref());
+        self.current = self.trie.lower_bound(&key, true);
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = match self.current {
+            Some(node) => self.trie.nodes[node].next,
+            None => self.trie.head,
+        };
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = match self.current {
+            Some(node) => self.trie.nodes[node].prev,
+            None => self.trie.tail,
+        };
+    }
+
+    pub fn key(&self) -> Option<&'a [u8]> {
+        let trie: &'a Trie<T, C> = self.trie;
+        trie.nodes[self.current?].key.as_deref()
+    }
+
+    pub fn value(&self) -> Option<&'a T> {
+        let trie: &'a Trie<T, C> = self.trie;
+        trie.nodes[self.current?].val.as_ref()
+    }
+
+    pub fn key_value(&self) -> Option<(&'a [u8], &'a T)> {
+        Some((self.key()?, self.value()?))
+    }
+}
+
+impl<'a, T, C> CursorMut<'a, T, C>
+where
+    C: Container<T>,
+{
+    /// Moves as `step` moves a read-only cursor at the same position.
+    fn step(&mut self, step: impl FnOnce(&mut Cursor<'_, T, C>)) {
+        let mut cursor = self.as_cursor();
+        step(&mut cursor);
+        self.current = cursor.current;
+    }
+
+    /// Like `Cursor::seek`.
+    pub fn seek(&mut self, key: impl AsRef<[u8]>) {
+        self.step(|cursor| cursor.seek(key));
+    }
+
+    pub fn move_next(&mut self) {
+        self.step(|cursor| cursor.move_next());
+    }
+
+    pub fn move_prev(&mut self) {
+        self.step(|cursor| cursor.move_prev());
+    }
+
+    pub fn key(&self) -> Option<&[u8]> {
+        self.as_cursor().key()
+    }
+
+    pub fn value(&self) -> Option<&T> {
+        self.as_cursor().value()
+    }
+
+    pub fn value_mut(&mut self) -> Option<&mut T> {
+        self.trie.nodes[self.current?].val.as_mut()
+    }
+
+    /// A read-only cursor at the same position.
+    pub fn as_cursor(&self) -> Cursor<'_, T, C> {
+        Cursor {
+            trie: self.trie,
+            current: self.current,
+        }
+    }
+
+    /// Removes the current entry and moves to the next one.
+    pub fn remove_current(&mut self) -> Option<(Vec<u8>, T)> {
+        let node = self.current?;
+        let key = self.trie.nodes[node].key.clone()?;
+        // only nodes left without a value are pruned, so `next` stays valid.
+        self.current = self.trie.nodes[node].next;
+        let val = self.trie.remove(&key)?;
+        Some((key, val))
+    }
+
+    /// Inserts an entry right after the current one, or at the front from the
+    /// ghost position. The cursor does not move.
+    ///
+    /// Fails with `ErrorKind::KeyNotSorted` unless `key` sorts strictly
+    /// between the current entry and the next one.
+    pub fn insert_after(&mut self, key: Vec<u8>, val: T) -> Result<(), TrieError> {
+        let (prev, next) = match self.current {
+            Some(node) => (Some(node), self.trie.nodes[node].next),
+            None => (None, self.trie.head),
+        };
+        self.insert_between(prev, next, key, val)
+    }
+
+    /// Inserts an entry right before the current one, or at the back from the
+    /// ghost position. The cursor does not move.
+    ///
+    /// Fails with `ErrorKind::KeyNotSorted` unless `key` sorts strictly
+    /// between the previous entry and the current one.
+    pub fn insert_before(&mut self, key: Vec<u8>, val: T) -> Result<(), TrieError> {
+        let (prev, next) = match self.current {
+            Some(node) => (self.trie.nodes[node].prev, Some(node)),
+            None => (self.trie.tail, None),
+        };
+        self.insert_between(prev, next, key, val)
+    }
+
+    fn insert_between(
+        &mut self,
+        prev: Link<T, C>,
+        next: Link<T, C>,
+        key: Vec<u8>,
+        val: T,
+    ) -> Result<(), TrieError> {
+        let stored = self.trie.normalize(&key)?.0.into_owned();
+        let nodes = &self.trie.nodes;
+        let after_prev = prev.is_none_or(|p| nodes[p].key.as_deref() < Some(&stored[..]));
+        let before_next = next.is_none_or(|n| nodes[n].key.as_deref() > Some(&stored[..]));
+        if !after_prev || !before_next {
+            return Err(TrieError::from(ErrorKind::KeyNotSorted));
+        }
+        self.trie.set(key, val)?;
+        Ok(())
+    }
+}
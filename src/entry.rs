@@ -0,0 +1,143 @@
+use crate::trie::{Container, NodeId, Trie};
+
+/// A view into a single key of a `Trie`, obtained from `Trie::entry`.
+pub enum Entry<'a, T: 'a, C: 'a> {
+    Occupied(OccupiedEntry<'a, T, C>),
+    Vacant(VacantEntry<'a, T, C>),
+}
+
+/// A key holding a value. It remembers the path to its node, so removing
+/// it updates the counts and prunes without another descent.
+pub struct OccupiedEntry<'a, T: 'a, C: 'a> {
+    pub(crate) trie: &'a mut Trie<T, C>,
+    /// The ancestors of `node`, from the root down.
+    pub(crate) path: Vec<NodeId<T, C>>,
+    pub(crate) node: NodeId<T, C>,
+}
+
+/// A key with no value yet. It remembers the deepest existing node on the
+/// key's path and the ancestors above it, so inserting only creates what is
+/// missing below it and counts the value along the path already walked.
+pub struct VacantEntry<'a, T: 'a, C: 'a> {
+    pub(crate) trie: &'a mut Trie<T, C>,
+    pub(crate) key: Vec<u8>,
+    /// The ancestors of `node`, from the root down.
+    pub(crate) path: Vec<NodeId<T, C>>,
+    pub(crate) node: NodeId<T, C>,
+    pub(crate) depth: usize,
+}
+
+impl<'a, T, C> Entry<'a, T, C>
+where
+    C: Container<T>,
+{
+    pub fn key(&self) -> &[u8] {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, T, C> Entry<'a, T, C>
+where
+    T: Default,
+    C: Container<T>,
+{
+    pub fn or_default(self) -> &'a mut T {
+        self.or_insert_with(T::default)
+    }
+}
+
+impl<'a, T, C> OccupiedEntry<'a, T, C>
+where
+    C: Container<T>,
+{
+    pub fn key(&self) -> &[u8] {
+        self.trie.nodes[self.node]
+            .key
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self) -> &T {
+        self.trie.nodes[self.node]
+            .val
+            .as_ref()
+            .expect("occupied entry")
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.trie.nodes[self.node]
+            .val
+            .as_mut()
+            .expect("occupied entry")
+    }
+
+    pub fn into_mut(self) -> &'a mut T {
+        let OccupiedEntry { trie, node, .. } = self;
+        trie.nodes[node].val.as_mut().expect("occupied entry")
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, val: T) -> T {
+        std::mem::replace(self.get_mut(), val)
+    }
+
+    /// Removes the entry from the trie, returning its value.
+    pub fn remove(self) -> T {
+        self.trie
+            .remove_at(self.path, self.node)
+            .expect("occupied entry")
+    }
+}
+
+impl<'a, T, C> VacantEntry<'a, T, C>
+where
+    C: Container<T>,
+{
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn into_key(self) -> Vec<u8> {
+        self.key
+    }
+
+    pub fn insert(self, val: T) -> &'a mut T {
+        let VacantEntry {
+            trie,
+            key,
+            mut path,
+            node,
+            depth,
+        } = self;
+        let node = trie.make_path(node, &key[depth..], &mut path);
+        trie.fill(&path, node, key, val);
+        trie.nodes[node].val.as_mut().expect("just inserted")
+    }
+}
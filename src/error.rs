@@ -3,6 +3,9 @@
 pub enum ErrorKind {
     Notfound,
     KeySizeNotMatch,
+    InvalidKey,
+    KeyNotSorted,
+    KeyDuplicated,
     Unexpect,
     Common(u16, String),
 }
@@ -31,7 +34,7 @@ impl std::fmt::Display for TrieError {
 
 impl TrieError {
     pub fn new(kind: ErrorKind) -> TrieError {
-        TrieError { kind: kind }
+        TrieError { kind }
     }
     pub fn from(kind: ErrorKind) -> TrieError {
         TrieError { kind }
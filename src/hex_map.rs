@@ -0,0 +1,143 @@
+use crate::trie::{Container, Link, NodeId};
+use std::fmt::Debug;
+
+/// A sixteen way container for keys spelled one nibble per byte, as produced
+/// by `to_nibbles`. Digests and hex ids spread evenly over such keys, so
+/// their nodes stay small where a `ByteMap` would be mostly empty slots.
+#[derive(Debug)]
+pub struct HexMap<T> {
+    buckets: [Link<T, HexMap<T>>; 16],
+}
+
+/// Splits every byte of `key` into its high and low nibble.
+pub fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    let mut nibbles = Vec::with_capacity(key.len() * 2);
+    for b in key {
+        nibbles.push(b >> 4);
+        nibbles.push(b & 0x0f);
+    }
+    nibbles
+}
+
+/// Joins pairs of nibbles back into bytes, the inverse of `to_nibbles`. A
+/// trailing odd nibble becomes the high half of the last byte.
+pub fn from_nibbles(nibbles: &[u8]) -> Vec<u8> {
+    nibbles
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+impl<T> std::fmt::Display for HexMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut msg = String::from("[");
+        for i in 0..16 {
+            if let Some(node) = self.buckets[i] {
+                msg = format!("{},{},", msg, node.index());
+            } else {
+                msg = format!("{},{},", msg, "None");
+            }
+        }
+        write!(f, "{}]", msg)?;
+        Ok(())
+    }
+}
+
+impl<T> Container<T> for HexMap<T> {
+    fn new() -> HexMap<T> {
+        HexMap {
+            buckets: [
+                None, None, None, None, None, None, None, None, None, None, None, None, None, None,
+                None, None,
+            ],
+        }
+    }
+    fn get(&self, k: u8) -> Link<T, Self> {
+        *self.buckets.get(k as usize)?
+    }
+
+    fn set(&mut self, k: u8, v: NodeId<T, Self>) {
+        if let Some(bucket) = self.buckets.get_mut(k as usize) {
+            *bucket = Some(v);
+        }
+    }
+    fn del(&mut self, k: u8) -> bool {
+        match self.buckets.get_mut(k as usize) {
+            Some(bucket) => bucket.take().is_some(),
+            None => false,
+        }
+    }
+    fn prev(&self, k: u8) -> Link<T, Self> {
+        let k = (k as usize).min(16);
+        for i in (0..k).rev() {
+            if self.buckets[i].is_some() {
+                return self.buckets[i];
+            }
+        }
+        None
+    }
+
+    fn next(&self, k: u8) -> Link<T, Self> {
+        for i in k as usize + 1..16 {
+            if self.buckets[i].is_some() {
+                return self.buckets[i];
+            }
+        }
+        None
+    }
+    fn is_head(&self, k: u8) -> bool {
+        for i in 0..16 {
+            if self.buckets[i].is_some() {
+                return i == k as usize;
+            }
+        }
+        false
+    }
+    fn head(&self) -> Link<T, Self> {
+        for i in 0..16 {
+            if self.buckets[i].is_some() {
+                return self.buckets[i];
+            }
+        }
+        None
+    }
+
+    fn is_tail(&self, k: u8) -> bool {
+        for i in (0..16).rev() {
+            if self.buckets[i].is_some() {
+                return i == k as usize;
+            }
+        }
+        false
+    }
+
+    fn tail(&self) -> Link<T, Self> {
+        for i in (0..16).rev() {
+            if self.buckets[i].is_some() {
+                return self.buckets[i];
+            }
+        }
+        None
+    }
+    fn keys(&self) -> Vec<u8> {
+        let mut keys = Vec::with_capacity(16);
+        for i in 0..16 {
+            if self.buckets[i].is_some() {
+                keys.push(i as u8)
+            }
+        }
+        keys
+    }
+
+    fn pad() -> u8 {
+        0
+    }
+
+    fn valid(k: u8) -> bool {
+        k < 16
+    }
+
+    fn capacity(&self) -> usize {
+        16
+    }
+}
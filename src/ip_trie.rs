@@ -0,0 +1,112 @@
+use crate::bit_map::BitMap;
+use crate::error::{ErrorKind, TrieError};
+use crate::trie::Trie;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// A routing table of IPv4 and IPv6 CIDR prefixes answering longest-prefix
+/// match queries. Each prefix is stored one bit per key byte in a `BitMap`
+/// trie, so a `/n` route is a key of length `n`.
+#[derive(Debug)]
+pub struct IpTrie<T> {
+    v4: Trie<T, BitMap<T>>,
+    v6: Trie<T, BitMap<T>>,
+}
+
+impl<T> Default for IpTrie<T> {
+    fn default() -> Self {
+        IpTrie::new()
+    }
+}
+
+impl<T> IpTrie<T> {
+    pub fn new() -> IpTrie<T> {
+        IpTrie {
+            v4: Trie::variable(),
+            v6: Trie::variable(),
+        }
+    }
+
+    /// Stores `val` for the prefix `addr/prefix_len`, returning the value it
+    /// replaced. Bits of `addr` past the prefix are ignored.
+    pub fn insert(&mut self, addr: IpAddr, prefix_len: u8, val: T) -> Result<Option<T>, TrieError> {
+        let key = bits(addr, prefix_len)?;
+        self.table_mut(addr).set(key, val)
+    }
+
+    /// The value stored for exactly the prefix `addr/prefix_len`.
+    pub fn get(&self, addr: IpAddr, prefix_len: u8) -> Option<&T> {
+        let key = bits(addr, prefix_len).ok()?;
+        self.table(addr).get(key)
+    }
+
+    /// Removes the prefix `addr/prefix_len`, returning its value.
+    pub fn remove(&mut self, addr: IpAddr, prefix_len: u8) -> Option<T> {
+        let key = bits(addr, prefix_len).ok()?;
+        self.table_mut(addr).remove(key)
+    }
+
+    /// The most specific prefix containing `addr`, as the network address,
+    /// its prefix length and its value.
+    pub fn longest_match(&self, addr: IpAddr) -> Option<(IpAddr, u8, &T)> {
+        let max = max_len(addr);
+        let key = bits(addr, max).ok()?;
+        let (prefix, val) = self.table(addr).longest_prefix_match(key)?;
+        Some((network(addr, prefix), prefix.len() as u8, val))
+    }
+
+    pub fn len(&self) -> usize {
+        self.v4.len() + self.v6.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn table(&self, addr: IpAddr) -> &Trie<T, BitMap<T>> {
+        match addr {
+            IpAddr::V4(_) => &self.v4,
+            IpAddr::V6(_) => &self.v6,
+        }
+    }
+
+    fn table_mut(&mut self, addr: IpAddr) -> &mut Trie<T, BitMap<T>> {
+        match addr {
+            IpAddr::V4(_) => &mut self.v4,
+            IpAddr::V6(_) => &mut self.v6,
+        }
+    }
+}
+
+fn max_len(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Spells the first `prefix_len` bits of `addr` as a key of `0`/`1` bytes.
+fn bits(addr: IpAddr, prefix_len: u8) -> Result<Vec<u8>, TrieError> {
+    if prefix_len > max_len(addr) {
+        return Err(TrieError::from(ErrorKind::InvalidKey));
+    }
+    let octets = match addr {
+        IpAddr::V4(addr) => addr.octets().to_vec(),
+        IpAddr::V6(addr) => addr.octets().to_vec(),
+    };
+    Ok((0..prefix_len as usize)
+        .map(|i| (octets[i / 8] >> (7 - i % 8)) & 1)
+        .collect())
+}
+
+/// The address whose leading bits are `key` and whose host bits are zero,
+/// in the family of `addr`.
+fn network(addr: IpAddr, key: &[u8]) -> IpAddr {
+    let mut octets = [0u8; 16];
+    for (i, bit) in key.iter().enumerate() {
+        octets[i / 8] |= bit << (7 - i % 8);
+    }
+    match addr {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3])),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(octets)),
+    }
+}
@@ -2,26 +2,48 @@ pub mod error;
 pub mod nmap;
 pub mod trie;
 pub mod byte_map;
+pub mod entry;
+pub mod cursor;
+pub mod bit_map;
+pub mod ip_trie;
+pub mod hex_map;
+pub mod art_map;
+pub mod arena;
 
 #[cfg(test)]
 mod tests {
-    use std::vec;
+    use std::collections::BTreeMap;
 
     use bson::oid::ObjectId;
 
+    use crate::art_map::ArtMap;
+    use crate::entry::Entry;
+    use crate::hex_map::{from_nibbles, to_nibbles, HexMap};
+    use crate::ip_trie::IpTrie;
+    use crate::nmap::Nmap;
     use crate::trie::Trie;
 
+    /// A small linear congruential generator, so the randomized tests are
+    /// reproducible without pulling in a crate for it.
+    fn lcg(seed: u32) -> impl Iterator<Item = u32> {
+        std::iter::successors(Some(seed), |s| {
+            Some(s.wrapping_mul(1103515245).wrapping_add(12345))
+        })
+        .skip(1)
+    }
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
     #[test]
+    #[cfg_attr(miri, ignore)]
     fn test_trie() {
         let size = 10000000;
-        let mut trie = Trie::new(12);
+        let mut trie: Trie<Vec<u8>> = Trie::new(12);
         let mut keys = Vec::with_capacity(size);
-        for i in 0..size {
+        for _ in 0..size {
             let key = ObjectId::new().bytes().to_vec();
             if let Err(err) = trie.set(key.clone(), key.clone()) {
                 println!("{}", err);
@@ -30,13 +52,1013 @@ mod tests {
             keys.push(key)
         }
 
-        for i in 0..size {
-            let key = keys[i].clone();
-            if let Ok(v1) = trie.get(key.clone()) {
-                assert_eq!(v1, key.clone());
+        for key in keys.iter() {
+            if let Some(v1) = trie.get(key) {
+                assert_eq!(v1, key);
             } else {
                 assert_eq!(1, 2);
             }
         }
     }
+
+    #[test]
+    fn test_nmap_trie() {
+        let mut trie: Trie<u32, Nmap<u32>> = Trie::new(3);
+        for i in [7, 42, 100, 999, 5] {
+            let key = format!("{:03}", i).into_bytes();
+            trie.set(key, i).unwrap();
+        }
+        assert_eq!(trie.get(b"042"), Some(&42));
+        assert_eq!(trie.get(b"999"), Some(&999));
+        assert!(trie.get(b"043").is_none());
+        assert!(trie.set(b"0x1".to_vec(), 1).is_err());
+
+        assert!(trie.del(b"042"));
+        assert!(!trie.del(b"042"));
+        assert!(trie.get(b"042").is_none());
+        assert_eq!(trie.get(b"007"), Some(&7));
+    }
+
+    #[test]
+    fn test_byte_map_trie() {
+        let mut trie: Trie<u8> = Trie::new(2);
+        for i in 0..=255u8 {
+            trie.set(vec![i, 255 - i], i).unwrap();
+        }
+        for i in 0..=255u8 {
+            assert_eq!(trie.get([i, 255 - i]), Some(&i));
+        }
+        for i in (0..=255u8).step_by(2) {
+            assert!(trie.del([i, 255 - i]));
+        }
+        for i in 0..=255u8 {
+            assert_eq!(trie.get([i, 255 - i]).is_some(), i % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut trie: Trie<u32> = Trie::new(2);
+        let mut keys = Vec::new();
+        for i in 0..1000u32 {
+            let key = vec![(i * 7 % 256) as u8, (i * 13 % 256) as u8];
+            if trie.set(key.clone(), i).is_ok() && !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys.sort();
+
+        let iter = trie.iter();
+        assert_eq!(iter.len(), keys.len());
+        let got: Vec<Vec<u8>> = iter.map(|(k, _)| k.to_vec()).collect();
+        assert_eq!(got, keys);
+        let got: Vec<Vec<u8>> = trie.iter().rev().map(|(k, _)| k.to_vec()).collect();
+        assert_eq!(got, keys.iter().rev().cloned().collect::<Vec<_>>());
+
+        let mut iter = trie.iter();
+        assert_eq!(iter.next().map(|(k, _)| k), Some(&keys[0][..]));
+        assert_eq!(
+            iter.next_back().map(|(k, _)| k),
+            keys.last().map(|k| &k[..])
+        );
+        assert_eq!(iter.len(), keys.len() - 2);
+
+        for (_, v) in trie.iter_mut() {
+            *v += 1;
+        }
+        for (k, v) in &trie {
+            assert_eq!(trie.get(k), Some(v));
+        }
+
+        let owned: Vec<(Vec<u8>, u32)> = trie.into_iter().collect();
+        assert_eq!(owned.len(), keys.len());
+        assert!(owned.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_range() {
+        let mut trie: Trie<u32> = Trie::new(3);
+        let mut map = BTreeMap::new();
+        for (i, seed) in (0..2000).zip(lcg(7)) {
+            let key = seed.to_be_bytes()[..3].to_vec();
+            trie.set(key.clone(), i).unwrap();
+            map.insert(key, i);
+        }
+        let probes = [
+            vec![0, 0, 0],
+            vec![17, 200, 3],
+            vec![128, 0, 0],
+            vec![255, 255, 255],
+        ];
+        for lo in probes.iter() {
+            for hi in probes.iter() {
+                let got: Vec<_> = trie
+                    .range(&lo[..]..&hi[..])
+                    .map(|(k, v)| (k.to_vec(), *v))
+                    .collect();
+                let want: Vec<_> = if lo <= hi {
+                    map.range(lo.clone()..hi.clone())
+                        .map(|(k, v)| (k.clone(), *v))
+                        .collect()
+                } else {
+                    vec![]
+                };
+                assert_eq!(got, want);
+                let got: Vec<_> = trie
+                    .range(lo.clone()..=hi.clone())
+                    .rev()
+                    .map(|(_, v)| *v)
+                    .collect();
+                let want: Vec<_> = if lo <= hi {
+                    map.range(lo.clone()..=hi.clone())
+                        .rev()
+                        .map(|(_, v)| *v)
+                        .collect()
+                } else {
+                    vec![]
+                };
+                assert_eq!(got, want);
+            }
+            assert!(trie
+                .range(lo.clone()..)
+                .map(|(_, v)| *v)
+                .eq(map.range(lo.clone()..).map(|(_, v)| *v)));
+            assert!(trie
+                .range(..lo.clone())
+                .map(|(_, v)| *v)
+                .eq(map.range(..lo.clone()).map(|(_, v)| *v)));
+        }
+        assert_eq!(trie.range::<Vec<u8>, _>(..).count(), map.len());
+
+        let (k, v) = map.iter().nth(100).unwrap();
+        assert_eq!(trie.gte(k), Some(v));
+        assert_eq!(trie.gt(k), map.range(k.clone()..).nth(1).map(|(_, v)| v));
+        assert_eq!(trie.lte(k), Some(v));
+        assert_eq!(
+            trie.lt(k),
+            map.range(..k.clone()).next_back().map(|(_, v)| v)
+        );
+        assert_eq!(trie.lt([0, 0, 0]), None);
+        assert_eq!(trie.gt([255, 255, 255]), None);
+    }
+
+    #[test]
+    fn test_prefix() {
+        let mut trie: Trie<u32, Nmap<u32>> = Trie::new(4);
+        for i in 0..10000 {
+            trie.set(format!("{:04}", i).into_bytes(), i).unwrap();
+        }
+        let got: Vec<u32> = trie.prefix_iter(b"12").map(|(_, v)| *v).collect();
+        assert_eq!(got, (1200..1300).collect::<Vec<_>>());
+        assert_eq!(
+            trie.prefix_iter(b"999").next_back(),
+            Some((&b"9999"[..], &9999))
+        );
+        assert_eq!(trie.prefix_count(b"4"), 1000);
+        assert_eq!(trie.prefix_count(b"0420"), 1);
+        assert_eq!(trie.prefix_count(b"04201"), 0);
+        assert_eq!(trie.prefix_count(b""), 10000);
+
+        assert_eq!(trie.remove_prefix(b"5"), 1000);
+        assert_eq!(trie.prefix_count(b"5"), 0);
+        assert_eq!(trie.remove_prefix(b"5"), 0);
+        assert_eq!(trie.iter().len(), 9000);
+        assert_eq!(trie.gte(b"5000"), Some(&6000));
+        assert_eq!(trie.lt(b"6000"), Some(&4999));
+        assert_eq!(trie.remove_prefix(b"42"), 100);
+        assert_eq!(trie.rank(b"4300"), 4200);
+        assert_eq!(trie.select(4200), Some((&b"4300"[..], &4300)));
+
+        // whole subtrees go, including values on interior nodes and prefixes
+        // ending inside a compressed edge.
+        let words = ["", "a", "ab", "abc", "abcdef", "abd", "b", "ba", "bcdefg"];
+        for prefix in ["abc", "ab", "bcd", "a", "bcdefgh", "c", ""] {
+            let mut trie: Trie<usize> = Trie::variable().with_path_compression();
+            for (i, w) in words.iter().enumerate() {
+                trie.insert(w.as_bytes().to_vec(), i);
+            }
+            let mut left: Trie<usize> = Trie::variable().with_path_compression();
+            for (i, w) in words.iter().enumerate() {
+                if !w.starts_with(prefix) {
+                    left.insert(w.as_bytes().to_vec(), i);
+                }
+            }
+            assert_eq!(trie.remove_prefix(prefix), words.len() - left.len());
+            assert!(trie.iter().eq(left.iter()));
+            assert!(trie.iter().rev().eq(left.iter().rev()));
+            assert_eq!(trie.len(), left.len());
+            assert_eq!(trie.prefix_count(b"a"), left.prefix_count(b"a"));
+            assert_eq!(trie.stats().slots, left.stats().slots);
+            trie.insert(b"abcx".to_vec(), 99);
+            assert_eq!(trie.get(b"abcx"), Some(&99));
+        }
+    }
+
+    #[test]
+    fn test_variable_keys() {
+        let mut trie: Trie<usize> = Trie::variable();
+        let words = [
+            "", "a", "ab", "abc", "abd", "b", "ba", "path/to", "path", "aa",
+        ];
+        for (i, w) in words.iter().enumerate() {
+            trie.set(w.as_bytes().to_vec(), i).unwrap();
+        }
+        for (i, w) in words.iter().enumerate() {
+            assert_eq!(trie.get(w.as_bytes()), Some(&i));
+        }
+        assert!(trie.get(b"ac").is_none());
+
+        let mut sorted: Vec<&str> = words.to_vec();
+        sorted.sort();
+        let keys: Vec<&[u8]> = trie.iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            sorted.iter().map(|w| w.as_bytes()).collect::<Vec<_>>()
+        );
+        assert_eq!(trie.prefix_count(b"ab"), 3);
+        assert_eq!(trie.gt(b"abd"), Some(&5));
+        assert_eq!(trie.lt(b"b"), Some(&4));
+
+        // interior values survive removing their children and vice versa
+        assert!(trie.del(b"abc"));
+        assert!(trie.del(b"a"));
+        assert!(!trie.del(b"a"));
+        assert_eq!(trie.get(b"ab"), Some(&2));
+        assert_eq!(trie.get(b"abd"), Some(&4));
+        assert!(trie.del(b""));
+        let keys: Vec<&[u8]> = trie.iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            [&b"aa"[..], b"ab", b"abd", b"b", b"ba", b"path", b"path/to"]
+        );
+    }
+
+    #[test]
+    fn test_padded_keys() {
+        let mut trie: Trie<u32, Nmap<u32>> = Trie::padded(6);
+        for i in [42, 7, 100000, 999, 31337] {
+            trie.set(i.to_string().into_bytes(), i).unwrap();
+        }
+        assert_eq!(trie.get(b"42"), Some(&42));
+        assert_eq!(trie.get(b"000042"), Some(&42));
+        assert!(trie.set(b"1234567".to_vec(), 0).is_err());
+
+        let keys: Vec<&[u8]> = trie.iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            [&b"000007"[..], b"000042", b"000999", b"031337", b"100000"]
+        );
+        assert_eq!(trie.gt(b"999"), Some(&31337));
+        assert_eq!(trie.lte(b"41"), Some(&7));
+        let vals: Vec<u32> = trie
+            .range(&b"42"[..]..&b"999"[..])
+            .map(|(_, v)| *v)
+            .collect();
+        assert_eq!(vals, [42]);
+        let vals: Vec<u32> = trie
+            .range(&b"8"[..]..=&b"31337"[..])
+            .map(|(_, v)| *v)
+            .collect();
+        assert_eq!(vals, [42, 999, 31337]);
+
+        let (key, len) = trie.normalize(b"123").unwrap();
+        assert_eq!((&key[..], len), (&b"000123"[..], 3));
+        assert!(trie.del(b"7"));
+        assert!(trie.get(b"000007").is_none());
+
+        let mut bytes: Trie<u8> = Trie::padded(4);
+        bytes.set(vec![1, 2], 1).unwrap();
+        assert_eq!(bytes.get([0, 0, 1, 2]), Some(&1));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut trie: Trie<String> = Trie::variable();
+        for i in 0..100 {
+            trie.set(format!("key{}", i).into_bytes(), i.to_string())
+                .unwrap();
+        }
+        trie.clear();
+        assert_eq!(trie.iter().len(), 0);
+        assert!(trie.get(b"key1").is_none());
+        trie.set(b"key1".to_vec(), "one".to_string()).unwrap();
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            [(&b"key1"[..], &"one".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut trie: Trie<String> = Trie::variable();
+        for w in ["", "a", "abc", "b", "ba", "bb"] {
+            trie.set(w.as_bytes().to_vec(), w.to_uppercase()).unwrap();
+        }
+        let fork = trie.clone();
+        assert!(trie.del(b"abc"));
+        trie.set(b"c".to_vec(), "C".to_string()).unwrap();
+        for (_, v) in trie.iter_mut() {
+            v.push('!');
+        }
+
+        let keys: Vec<&[u8]> = fork.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, [&b""[..], b"a", b"abc", b"b", b"ba", b"bb"]);
+        assert_eq!(fork.iter().len(), 6);
+        assert_eq!(fork.get(b"abc"), Some(&"ABC".to_string()));
+        assert!(fork.get(b"c").is_none());
+        assert_eq!(
+            fork.iter().next_back(),
+            Some((&b"bb"[..], &"BB".to_string()))
+        );
+        assert_eq!(trie.get(b"bb"), Some(&"BB!".to_string()));
+    }
+
+    #[test]
+    fn test_non_clone_values() {
+        struct Buffer(Vec<u8>);
+
+        let mut trie: Trie<Buffer, Nmap<Buffer>> = Trie::new(2);
+        trie.set(b"01".to_vec(), Buffer(vec![1])).unwrap();
+        trie.set(b"02".to_vec(), Buffer(vec![2])).unwrap();
+        trie.get_mut(b"01").unwrap().0.push(10);
+        assert!(trie.get_mut(b"03").is_none());
+        assert_eq!(trie.get(b"01").map(|b| &b.0[..]), Some(&[1, 10][..]));
+        assert_eq!(trie.gt(b"01").map(|b| &b.0[..]), Some(&[2][..]));
+    }
+
+    #[test]
+    fn test_map_api() {
+        let mut trie: Trie<&str> = Trie::new(2);
+        assert!(trie.is_empty());
+        assert_eq!(trie.insert(b"ab".to_vec(), "x"), None);
+        assert_eq!(trie.insert(b"ab".to_vec(), "y"), Some("x"));
+        assert_eq!(trie.set(b"cd".to_vec(), "z").unwrap(), None);
+        assert!(trie.set(b"abc".to_vec(), "w").is_err());
+        assert_eq!(trie.len(), 2);
+        assert!(trie.contains_key(b"ab"));
+        assert!(!trie.contains_key(b"ac"));
+
+        assert_eq!(trie.remove(b"ab"), Some("y"));
+        assert_eq!(trie.remove(b"ab"), None);
+        assert_eq!(trie.remove(b"abc"), None);
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.iter().collect::<Vec<_>>(), [(&b"cd"[..], &"z")]);
+        assert!(trie.del(b"cd"));
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_bad_key() {
+        let mut trie: Trie<u8, Nmap<u8>> = Trie::new(2);
+        trie.insert(b"a1".to_vec(), 0);
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut counts: Trie<u32> = Trie::variable();
+        for w in "the cat and the hat and the bat".split(' ') {
+            *counts.entry(w.as_bytes().to_vec()).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get(b"the"), Some(&3));
+        assert_eq!(counts.get(b"and"), Some(&2));
+        assert_eq!(counts.len(), 5);
+
+        counts
+            .entry(b"cat".to_vec())
+            .and_modify(|c| *c += 10)
+            .or_default();
+        counts
+            .entry(b"cow".to_vec())
+            .and_modify(|c| *c += 10)
+            .or_default();
+        assert_eq!(counts.get(b"cat"), Some(&11));
+        assert_eq!(counts.get(b"cow"), Some(&0));
+        assert_eq!(*counts.entry(b"ca".to_vec()).or_insert_with(|| 7), 7);
+
+        match counts.entry(b"hat".to_vec()) {
+            Entry::Occupied(mut e) => {
+                assert_eq!(e.key(), b"hat");
+                assert_eq!(e.insert(5), 1);
+                assert_eq!(e.remove(), 5);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        match counts.entry(b"hat".to_vec()) {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(e) => assert_eq!(e.into_key(), b"hat"),
+        }
+        let keys: Vec<&[u8]> = counts.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, [&b"and"[..], b"bat", b"ca", b"cat", b"cow", b"the"]);
+
+        // inserts and removals through entries keep the chain, counts and
+        // pruning in step with a map, with and without path compression.
+        for compressed in [false, true] {
+            let mut trie: Trie<usize> = Trie::variable();
+            if compressed {
+                trie = trie.with_path_compression();
+            }
+            let mut map = BTreeMap::new();
+            let words = ["ab", "abcd", "b", "", "abc", "ba", "a", "abce", "bb"];
+            for (i, w) in words.iter().enumerate() {
+                trie.entry(w.as_bytes().to_vec()).or_insert(i);
+                map.insert(w.as_bytes().to_vec(), i);
+            }
+            for w in ["abc", "a", "ba", "abce", "ab"] {
+                match trie.entry(w.as_bytes().to_vec()) {
+                    Entry::Occupied(e) => assert_eq!(Some(e.remove()), map.remove(w.as_bytes())),
+                    Entry::Vacant(_) => unreachable!(),
+                }
+                assert!(trie.iter().map(|(k, v)| (k.to_vec(), *v)).eq(map.clone()));
+                assert!(trie.iter().rev().map(|(k, _)| k).eq(map.keys().rev()));
+                for (i, (k, _)) in map.iter().enumerate() {
+                    assert_eq!(trie.rank(k), i);
+                }
+                assert_eq!(
+                    trie.prefix_count(b"ab"),
+                    map.keys().filter(|k| k.starts_with(b"ab")).count()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_collect_extend_index() {
+        let mut trie: Trie<u32, Nmap<u32>> = (0..100u32)
+            .rev()
+            .map(|i| (format!("{:03}", i).into_bytes(), i))
+            .collect();
+        assert_eq!(trie.len(), 100);
+        assert_eq!(trie[b"042"], 42);
+        assert_eq!(trie[&b"007".to_vec()], 7);
+        assert_eq!(trie.iter().next(), Some((&b"000"[..], &0)));
+
+        trie.extend((100..200u32).map(|i| (format!("{:03}", i).into_bytes(), i)));
+        trie.extend(vec![(b"042".to_vec(), 4242)]);
+        assert_eq!(trie.len(), 200);
+        assert_eq!(trie[&b"042"[..]], 4242);
+
+        let empty: Trie<u32> = Vec::new().into_iter().collect();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_missing() {
+        let trie: Trie<u32> = vec![(b"ab".to_vec(), 1)].into_iter().collect();
+        let _ = trie[b"ac"];
+    }
+
+    #[test]
+    fn test_from_sorted_iter() {
+        let mut keys: Vec<Vec<u8>> = (0..5000u32)
+            .map(|i| (i * 7919 % 65536).to_be_bytes().to_vec())
+            .collect();
+        keys.sort();
+        let bulk: Trie<usize> =
+            Trie::from_sorted_iter(4, keys.iter().cloned().enumerate().map(|(i, k)| (k, i)))
+                .unwrap();
+        let mut single: Trie<usize> = Trie::new(4);
+        for (i, key) in keys.iter().enumerate() {
+            single.insert(key.clone(), i);
+        }
+        assert_eq!(bulk.len(), keys.len());
+        assert!(bulk.iter().eq(single.iter()));
+        assert!(bulk.iter().rev().eq(single.iter().rev()));
+        assert_eq!(bulk.get(&keys[1234]), Some(&1234));
+        assert_eq!(bulk.prefix_count([0, 0]), single.prefix_count([0, 0]));
+
+        let unsorted = vec![(vec![1, 2], 0), (vec![1, 1], 1)];
+        assert!(Trie::<u8>::from_sorted_iter(2, unsorted).is_err());
+        let duplicated = vec![(vec![1, 2], 0), (vec![1, 2], 1)];
+        assert!(Trie::<u8>::from_sorted_iter(2, duplicated).is_err());
+        let short = vec![(vec![1, 2], 0), (vec![1], 1)];
+        assert!(Trie::<u8>::from_sorted_iter(2, short).is_err());
+    }
+
+    #[test]
+    fn test_first_last() {
+        let mut queue: Trie<u32> = Trie::new(12);
+        assert_eq!(queue.first_key_value(), None);
+        assert_eq!(queue.pop_first(), None);
+        let mut ids: Vec<ObjectId> = (0..100).map(|_| ObjectId::new()).collect();
+        ids.sort_by_key(|id| id.bytes());
+        for (i, id) in ids.iter().enumerate().rev() {
+            queue.insert(id.bytes().to_vec(), i as u32);
+        }
+        assert_eq!(queue.first_key_value(), Some((&ids[0].bytes()[..], &0)));
+        assert_eq!(queue.last_key_value(), Some((&ids[99].bytes()[..], &99)));
+
+        for i in 0..50 {
+            assert_eq!(queue.pop_first(), Some((ids[i].bytes().to_vec(), i as u32)));
+            assert_eq!(
+                queue.pop_last(),
+                Some((ids[99 - i].bytes().to_vec(), 99 - i as u32))
+            );
+        }
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop_last(), None);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let trie: Trie<u32, Nmap<u32>> = (0..50u32)
+            .map(|i| (format!("{:03}", i * 2).into_bytes(), i * 2))
+            .collect();
+        let mut cursor = trie.cursor();
+        assert_eq!(cursor.key_value(), Some((&b"000"[..], &0)));
+        cursor.seek(b"031");
+        assert_eq!(cursor.value(), Some(&32));
+        cursor.move_prev();
+        assert_eq!(cursor.value(), Some(&30));
+        cursor.seek(b"098");
+        cursor.move_next();
+        assert_eq!(cursor.key(), None);
+        cursor.move_next();
+        assert_eq!(cursor.value(), Some(&0));
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.value(), Some(&98));
+        cursor.seek(b"099");
+        assert_eq!(cursor.value(), None);
+
+        // page through in steps of 10, resuming after the last key seen
+        let mut trie = trie;
+        let mut pages = Vec::new();
+        let mut last: Option<Vec<u8>> = None;
+        loop {
+            let mut cursor = trie.cursor();
+            if let Some(last) = last.as_ref() {
+                cursor.seek(last);
+                cursor.move_next();
+            }
+            let page: Vec<u32> = (0..10)
+                .map_while(|_| {
+                    let v = cursor.value().copied();
+                    last = cursor.key().map(|k| k.to_vec()).or(last.take());
+                    cursor.move_next();
+                    v
+                })
+                .collect();
+            if page.is_empty() {
+                break;
+            }
+            pages.push(page);
+        }
+        assert_eq!(pages.len(), 5);
+        assert_eq!(pages[4][9], 98);
+
+        let mut cursor = trie.cursor_mut();
+        cursor.seek(b"010");
+        assert_eq!(cursor.remove_current(), Some((b"010".to_vec(), 10)));
+        assert_eq!(cursor.value(), Some(&12));
+        *cursor.value_mut().unwrap() += 1;
+        assert!(cursor.insert_after(b"013".to_vec(), 99).is_ok());
+        assert!(cursor.insert_after(b"014".to_vec(), 99).is_err());
+        assert!(cursor.insert_before(b"011".to_vec(), 98).is_ok());
+        assert!(cursor.insert_before(b"007".to_vec(), 97).is_err());
+        assert_eq!(cursor.value(), Some(&13));
+        cursor.move_next();
+        assert_eq!(cursor.key(), Some(&b"013"[..]));
+        while cursor.remove_current().is_some() {}
+        assert_eq!(trie.len(), 7);
+        assert_eq!(trie.last_key_value(), Some((&b"012"[..], &13)));
+    }
+
+    #[test]
+    fn test_rank_select() {
+        let mut trie: Trie<u32> = Trie::new(3);
+        let mut map = BTreeMap::new();
+        for (i, seed) in (0..2000).zip(lcg(11)) {
+            let key = seed.to_be_bytes()[..3].to_vec();
+            trie.insert(key.clone(), i);
+            map.insert(key, i);
+        }
+        for key in map.keys().step_by(3).cloned().collect::<Vec<_>>() {
+            trie.del(&key);
+            map.remove(&key);
+        }
+        let sorted: Vec<_> = map.iter().collect();
+        for (i, (k, v)) in sorted.iter().enumerate() {
+            assert_eq!(trie.rank(k), i);
+            assert_eq!(trie.select(i), Some((&k[..], *v)));
+        }
+        assert_eq!(trie.select(map.len()), None);
+        assert_eq!(
+            trie.rank([255, 255, 255]),
+            map.range(..vec![255, 255, 255]).count()
+        );
+
+        let probes = [vec![0, 0, 0], vec![17, 200, 3], vec![128, 0, 0]];
+        for lo in probes.iter() {
+            for hi in probes.iter() {
+                assert_eq!(
+                    trie.count_range(&lo[..]..&hi[..]),
+                    trie.range(&lo[..]..&hi[..]).count()
+                );
+                assert_eq!(
+                    trie.count_range(&lo[..]..=&hi[..]),
+                    trie.range(&lo[..]..=&hi[..]).count()
+                );
+            }
+        }
+        assert_eq!(trie.count_range::<[u8], _>(..), map.len());
+
+        let mut padded: Trie<u32, Nmap<u32>> = Trie::padded(4);
+        for i in [7, 42, 100, 999] {
+            padded.insert(i.to_string().into_bytes(), i);
+        }
+        assert_eq!(padded.rank(b"42"), 1);
+        assert_eq!(padded.count_range(..&b"42"[..]), 1);
+        assert_eq!(padded.count_range(&b"42"[..]..=&b"999"[..]), 3);
+
+        let copy = trie.clone();
+        assert_eq!(copy.select(100), trie.select(100));
+
+        // the root of an `ArtMap` trie this full takes the 256 slot layout.
+        let art: Trie<u32, ArtMap<u32>> = trie.iter().map(|(k, v)| (k.to_vec(), *v)).collect();
+        for i in (0..map.len()).step_by(7) {
+            assert_eq!(art.select(i), trie.select(i));
+            let (k, _) = trie.select(i).unwrap();
+            assert_eq!(art.rank(k), i);
+        }
+        assert_eq!(
+            art.count_range(&probes[1][..]..),
+            trie.count_range(&probes[1][..]..)
+        );
+
+        let mut words: Trie<usize> = Trie::variable();
+        for (i, w) in ["", "a", "ab", "abc", "b", "ba"].iter().enumerate() {
+            words.insert(w.as_bytes().to_vec(), i);
+        }
+        assert_eq!(words.rank(b"abc"), 3);
+        assert_eq!(words.rank(b"abz"), 4);
+        assert_eq!(words.select(0), Some((&b""[..], &0)));
+        assert_eq!(words.select(5), Some((&b"ba"[..], &5)));
+        assert_eq!(words.count_range(&b"a"[..]..&b"b"[..]), 3);
+        assert_eq!(words.prefix_count(b"ab"), 2);
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut codes: Trie<&str, Nmap<&str>> = Trie::variable();
+        for (code, country) in [("1", "US"), ("1242", "BS"), ("44", "GB"), ("7", "RU")] {
+            codes.insert(code.as_bytes().to_vec(), country);
+        }
+        assert_eq!(
+            codes.longest_prefix_match(b"12425551234"),
+            Some((&b"1242"[..], &"BS"))
+        );
+        assert_eq!(
+            codes.longest_prefix_match(b"12125551234"),
+            Some((&b"1"[..], &"US"))
+        );
+        assert_eq!(codes.longest_prefix_match(b"44"), Some((&b"44"[..], &"GB")));
+        assert_eq!(codes.longest_prefix_match(b"4"), None);
+        assert_eq!(codes.longest_prefix_match(b"33"), None);
+
+        codes.insert(vec![], "default");
+        assert_eq!(
+            codes.longest_prefix_match(b"33"),
+            Some((&b""[..], &"default"))
+        );
+    }
+
+    #[test]
+    fn test_ip_trie() {
+        use std::net::IpAddr;
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let mut routes = IpTrie::new();
+        routes.insert(ip("0.0.0.0"), 0, "default").unwrap();
+        routes.insert(ip("10.0.0.0"), 8, "corp").unwrap();
+        routes.insert(ip("10.1.2.0"), 24, "lab").unwrap();
+        routes.insert(ip("10.1.2.99"), 32, "host").unwrap();
+        routes.insert(ip("2001:db8::"), 32, "doc").unwrap();
+        assert!(routes.insert(ip("10.0.0.0"), 33, "bad").is_err());
+        assert_eq!(routes.len(), 5);
+
+        assert_eq!(
+            routes.longest_match(ip("10.1.2.3")),
+            Some((ip("10.1.2.0"), 24, &"lab"))
+        );
+        assert_eq!(
+            routes.longest_match(ip("10.1.2.99")),
+            Some((ip("10.1.2.99"), 32, &"host"))
+        );
+        assert_eq!(
+            routes.longest_match(ip("10.200.0.1")),
+            Some((ip("10.0.0.0"), 8, &"corp"))
+        );
+        assert_eq!(
+            routes.longest_match(ip("192.168.1.1")),
+            Some((ip("0.0.0.0"), 0, &"default"))
+        );
+        assert_eq!(
+            routes.longest_match(ip("2001:db8::1")),
+            Some((ip("2001:db8::"), 32, &"doc"))
+        );
+        assert_eq!(routes.longest_match(ip("2001:db9::1")), None);
+
+        // host bits past the prefix length are ignored.
+        assert_eq!(routes.get(ip("10.1.2.77"), 24), Some(&"lab"));
+        assert_eq!(routes.remove(ip("10.1.2.0"), 24), Some("lab"));
+        assert_eq!(
+            routes.longest_match(ip("10.1.2.3")),
+            Some((ip("10.0.0.0"), 8, &"corp"))
+        );
+        assert_eq!(routes.len(), 4);
+    }
+
+    #[test]
+    fn test_hex_map_trie() {
+        let digests: Vec<Vec<u8>> = (0..500u32)
+            .map(|i| i.wrapping_mul(2654435761).to_be_bytes().to_vec())
+            .collect();
+        let mut trie: Trie<u32, HexMap<u32>> = Trie::new(8);
+        let mut map = BTreeMap::new();
+        for (i, d) in digests.iter().enumerate() {
+            trie.insert(to_nibbles(d), i as u32);
+            map.insert(d.clone(), i as u32);
+        }
+        assert_eq!(trie.len(), map.len());
+        // nibble order is byte order, so iteration matches the raw keys.
+        let got: Vec<_> = trie.iter().map(|(k, v)| (from_nibbles(k), *v)).collect();
+        let want: Vec<_> = map.into_iter().collect();
+        assert_eq!(got, want);
+
+        assert_eq!(to_nibbles(&[0xab, 0x01]), vec![0xa, 0xb, 0x0, 0x1]);
+        assert_eq!(from_nibbles(&[0xa, 0xb, 0x0, 0x1]), vec![0xab, 0x01]);
+        assert_eq!(
+            trie.prefix_count([0xa]),
+            trie.range(&[0xa][..]..&[0xb][..]).count()
+        );
+        assert!(trie.set(vec![16; 8], 0).is_err());
+    }
+
+    #[test]
+    fn test_art_map() {
+        use crate::arena::Arena;
+        use crate::trie::{Container, NodeId, TrieNode};
+
+        let mut nodes = Arena::new();
+        let mut map: ArtMap<u32> = ArtMap::new();
+        let keys: Vec<u8> = (0..=255u8).map(|i| i.wrapping_mul(167)).collect();
+        for (i, &k) in keys.iter().enumerate() {
+            map.set(k, nodes.insert(TrieNode::new(k)));
+            let want = match i + 1 {
+                0..=4 => 4,
+                5..=16 => 16,
+                17..=48 => 48,
+                _ => 256,
+            };
+            assert_eq!(map.capacity(), want);
+            assert_eq!(map.len(), i + 1);
+        }
+        // replacing a child of a full node keeps the count.
+        map.set(0, nodes.insert(TrieNode::new(0)));
+        assert_eq!(map.len(), 256);
+        assert!(map.is_head(0) && map.is_tail(255) && !map.is_head(1) && !map.is_tail(0));
+        for &k in keys.iter().skip(200) {
+            assert!(map.del(k));
+            assert!(!map.del(k));
+        }
+        assert_eq!(map.capacity(), 256);
+        for &k in keys[10..200].iter() {
+            map.del(k);
+        }
+        assert_eq!(map.capacity(), 16);
+
+        let mut left = keys[..10].to_vec();
+        left.sort();
+        assert_eq!(map.keys(), left);
+        let key = |n: Option<NodeId<u32, ArtMap<u32>>>| n.map(|n| nodes[n].node_key);
+        assert_eq!(key(map.head()), Some(left[0]));
+        assert_eq!(key(map.tail()), Some(left[9]));
+        assert_eq!(key(map.next(left[3])), Some(left[4]));
+        assert_eq!(key(map.prev(left[3])), Some(left[2]));
+        assert_eq!(key(map.next(left[3] + 1)), Some(left[4]));
+        assert!(map.is_head(left[0]) && map.is_tail(left[9]));
+
+        let mut trie: Trie<u32, ArtMap<u32>> = Trie::new(3);
+        let mut bytes: Trie<u32> = Trie::new(3);
+        for (i, seed) in (0..3000).zip(lcg(5)) {
+            let key = seed.to_be_bytes()[1..].to_vec();
+            trie.insert(key.clone(), i);
+            bytes.insert(key, i);
+        }
+        for (k, _) in bytes
+            .iter()
+            .step_by(2)
+            .map(|(k, v)| (k.to_vec(), *v))
+            .collect::<Vec<_>>()
+        {
+            assert_eq!(trie.remove(&k), bytes.remove(&k));
+        }
+        assert!(trie.iter().eq(bytes.iter()));
+        assert_eq!(trie.gt([128, 0, 0]), bytes.gt([128, 0, 0]));
+        assert_eq!(trie.lt([128, 0, 0]), bytes.lt([128, 0, 0]));
+    }
+
+    #[test]
+    fn test_path_compression() {
+        let mut trie: Trie<u32> = Trie::variable().with_path_compression();
+        let mut plain: Trie<u32> = Trie::variable();
+        assert!(trie.path_compression() && !plain.path_compression());
+        let mut keys = vec![];
+        for (i, seed) in (0..3000).zip(lcg(3)) {
+            // a small alphabet and varied lengths make keys diverge inside
+            // compressed edges and end on interior nodes.
+            let len = (seed >> 28) as usize % 7;
+            let key: Vec<u8> = (0..len)
+                .map(|j| b'a' + ((seed >> (j * 3)) & 3) as u8)
+                .collect();
+            assert_eq!(trie.insert(key.clone(), i), plain.insert(key.clone(), i));
+            keys.push(key);
+        }
+        for key in keys.iter().step_by(3) {
+            assert_eq!(trie.remove(key), plain.remove(key));
+        }
+        *trie.entry(b"abcabc".to_vec()).or_insert(0) += 1;
+        *plain.entry(b"abcabc".to_vec()).or_insert(0) += 1;
+        assert!(trie.iter().eq(plain.iter()));
+
+        let probes: [&[u8]; 6] = [b"", b"a", b"abd", b"bb", b"cab", b"dddddddd"];
+        for p in probes {
+            assert_eq!(trie.get(p), plain.get(p));
+            assert_eq!(trie.gt(p), plain.gt(p));
+            assert_eq!(trie.lte(p), plain.lte(p));
+            assert_eq!(trie.rank(p), plain.rank(p));
+            assert_eq!(trie.prefix_count(p), plain.prefix_count(p));
+            assert!(trie.prefix_iter(p).eq(plain.prefix_iter(p)));
+            assert_eq!(trie.longest_prefix_match(p), plain.longest_prefix_match(p));
+            for q in probes {
+                assert!(trie.range(p..q).eq(plain.range(p..q)));
+            }
+        }
+        for i in 0..trie.len() {
+            assert_eq!(trie.select(i), plain.select(i));
+        }
+        assert!(trie.clone().iter().eq(plain.iter()));
+
+        let mut ids: Trie<usize> = Trie::new(12).with_path_compression();
+        let oids: Vec<ObjectId> = (0..1000).map(|_| ObjectId::new()).collect();
+        for (i, id) in oids.iter().enumerate() {
+            ids.insert(id.bytes().to_vec(), i);
+        }
+        for (i, id) in oids.iter().enumerate() {
+            assert_eq!(ids.get(id.bytes()), Some(&i));
+        }
+        while ids.pop_first().is_some() {}
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn test_container_bitmaps() {
+        use crate::arena::Arena;
+        use crate::byte_map::ByteMap;
+        use crate::trie::{Container, NodeId, TrieNode};
+
+        let mut nodes = Arena::new();
+        let mut map: ByteMap<u32> = ByteMap::new();
+        assert_eq!(map.head(), None);
+        // keys on both sides of every word boundary of the bitmap.
+        for k in [0u8, 63, 64, 127, 128, 200, 255] {
+            map.set(k, nodes.insert(TrieNode::new(k)));
+        }
+        let key = |n: Option<NodeId<u32, ByteMap<u32>>>| n.map(|n| nodes[n].node_key);
+        assert_eq!(map.keys(), vec![0, 63, 64, 127, 128, 200, 255]);
+        assert_eq!(key(map.head()), Some(0));
+        assert_eq!(key(map.tail()), Some(255));
+        assert_eq!(key(map.next(63)), Some(64));
+        assert_eq!(key(map.next(128)), Some(200));
+        assert_eq!(key(map.next(255)), None);
+        assert_eq!(key(map.prev(64)), Some(63));
+        assert_eq!(key(map.prev(190)), Some(128));
+        assert_eq!(key(map.prev(0)), None);
+        assert!(map.is_head(0) && map.is_tail(255) && !map.is_head(63));
+        for k in [0u8, 255] {
+            assert!(map.del(k));
+        }
+        assert_eq!(key(map.head()), Some(63));
+        assert_eq!(key(map.tail()), Some(200));
+
+        let mut digits: Trie<u32, Nmap<u32>> = Trie::new(1);
+        for k in [b'0', b'4', b'9'] {
+            digits.insert(vec![k], k as u32);
+        }
+        assert_eq!(digits.gt(b"4"), Some(&(b'9' as u32)));
+        assert_eq!(digits.lt(b"4"), Some(&(b'0' as u32)));
+        assert_eq!(digits.gt(b"9"), None);
+        assert_eq!(digits.pop_last(), Some((b"9".to_vec(), b'9' as u32)));
+        assert_eq!(digits.last_key_value(), Some((&b"4"[..], &(b'4' as u32))));
+    }
+
+    #[test]
+    fn test_arena() {
+        use crate::arena::Arena;
+
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        assert_eq!((arena[a], arena[b], arena.len()), ("a", "b", 2));
+        assert_eq!(arena.remove(a), "a");
+        assert_eq!(arena.get(a), None);
+        // the freed slot is handed out again before the buffer grows.
+        let c = arena.insert("c");
+        assert_eq!((c.index(), arena.slots()), (a.index(), 2));
+        arena[c] = "d";
+        assert_eq!(arena.get(c), Some(&"d"));
+        arena.clear();
+        assert!(arena.is_empty() && arena.get(b).is_none());
+
+        let mut trie: Trie<u32> = Trie::new(2);
+        for i in 0..100u16 {
+            trie.insert(i.to_be_bytes().to_vec(), i as u32);
+        }
+        let slots = trie.nodes.slots();
+        for i in 0..50u16 {
+            trie.remove(i.to_be_bytes());
+        }
+        for i in 100..150u16 {
+            trie.insert(i.to_be_bytes().to_vec(), i as u32);
+        }
+        assert_eq!(trie.nodes.slots(), slots);
+        assert!(trie.iter().map(|(_, v)| *v).eq(50..150));
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut trie: Trie<u32, Nmap<u32>> = Trie::new(2);
+        for i in 0..=10 {
+            trie.insert(format!("{:02}", i).into_bytes(), i);
+        }
+        let stats = trie.stats();
+        assert_eq!(stats.nodes_by_depth, vec![1, 2, 11]);
+        assert_eq!((stats.leaves, stats.interior), (11, 3));
+        assert_eq!(stats.nodes(), trie.nodes.len());
+        assert_eq!(stats.fan_out_by_depth, vec![2.0, 5.5, 0.0]);
+        assert_eq!((stats.slots, stats.empty_slots), (30, 17));
+        assert!((stats.empty_slot_ratio() - 17.0 / 30.0).abs() < 1e-9);
+        assert!(stats.heap_bytes >= 11 * 2 + 3 * std::mem::size_of::<Nmap<u32>>());
+
+        // an adaptive container sized to its children leaves fewer slots empty.
+        let mut art: Trie<u32, ArtMap<u32>> = Trie::new(3);
+        let mut bytes: Trie<u32> = Trie::new(3);
+        for (i, seed) in (0..2000).zip(lcg(11)) {
+            let key = seed.to_be_bytes()[1..].to_vec();
+            art.insert(key.clone(), i);
+            bytes.insert(key, i);
+        }
+        let (a, b) = (art.stats(), bytes.stats());
+        assert_eq!(a.nodes_by_depth, b.nodes_by_depth);
+        assert_eq!(a.fan_out_by_depth, b.fan_out_by_depth);
+        assert!(a.empty_slot_ratio() < b.empty_slot_ratio());
+        assert!(a.heap_bytes < b.heap_bytes);
+
+        for i in 0..=10 {
+            trie.remove(format!("{:02}", i));
+        }
+        let stats = trie.stats();
+        assert_eq!(stats.nodes_by_depth, vec![1]);
+        assert_eq!((stats.leaves, stats.interior), (1, 0));
+    }
+
+    #[test]
+    fn test_nmap_non_digit_bounds() {
+        let mut trie: Trie<u32, Nmap<u32>> = Trie::new(2);
+        for (i, key) in [b"10", b"20", b"55"].iter().enumerate() {
+            trie.insert(key.to_vec(), i as u32);
+        }
+        // bytes that can never be stored still order against the digits.
+        assert_eq!(trie.gte(b" "), Some(&0));
+        assert_eq!(trie.gt(b"5a"), None);
+        assert_eq!(trie.lt(b"a"), Some(&2));
+        assert_eq!(trie.lte(b"5 "), Some(&1));
+        assert_eq!(trie.range(&b"  "[..]..).count(), 3);
+        assert_eq!(trie.range(..&b"2a"[..]).count(), 2);
+        assert_eq!(trie.rank(b"a"), 3);
+        assert_eq!(trie.rank(b"5a"), 3);
+        assert_eq!(trie.rank(b"1 "), 0);
+        assert_eq!(trie.count_range(..&b"a"[..]), 3);
+        assert_eq!(trie.count_range(&b"1a"[..]..&b"5 "[..]), 1);
+    }
+
+    #[test]
+    fn test_iter_mut_debug() {
+        let mut trie: Trie<u32> = Trie::new(1);
+        for i in 0..4u8 {
+            trie.insert(vec![i], i as u32);
+        }
+        let mut iter = trie.iter_mut();
+        let v = iter.next().unwrap().1;
+        *v += 10;
+        // formatting reads only the entries not yet handed out.
+        assert_eq!(format!("{:?}", iter), "[([1], 1), ([2], 2), ([3], 3)]");
+        *v += 10;
+        let w = iter.next_back().unwrap().1;
+        assert_eq!(format!("{:?}", iter), "[([1], 1), ([2], 2)]");
+        *w += 1;
+        assert!(trie.iter().map(|(_, v)| *v).eq([20, 1, 2, 4]));
+    }
 }
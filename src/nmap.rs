@@ -1,10 +1,11 @@
-use crate::{trie::Container, trie::TrieNode};
+use crate::trie::{Container, Link, NodeId};
 use std::fmt::Debug;
-use std::ptr::NonNull;
 
 #[derive(Debug)]
 pub struct Nmap<T> {
-    buckets: [Option<NonNull<TrieNode<T>>>; 10],
+    buckets: [Link<T, Nmap<T>>; 10],
+    /// Bit `i` is set when `buckets[i]` holds a node.
+    bits: u16,
 }
 
 impl<T> std::fmt::Display for Nmap<T> {
@@ -12,7 +13,7 @@ impl<T> std::fmt::Display for Nmap<T> {
         let mut msg = String::from("[");
         for i in 0..10 {
             if let Some(node) = self.buckets[i] {
-                msg = format!("{},{},", msg, node.as_ptr() as u8);
+                msg = format!("{},{},", msg, node.index());
             } else {
                 msg = format!("{},{},", msg, "None");
             }
@@ -22,130 +23,106 @@ impl<T> std::fmt::Display for Nmap<T> {
     }
 }
 
+impl<T> Nmap<T> {
+    fn index(k: u8) -> Option<usize> {
+        if k.is_ascii_digit() {
+            return Some((k - b'0') as usize);
+        }
+        None
+    }
+
+    /// The smallest occupied bucket at or after `from`.
+    fn first_from(&self, from: usize) -> Option<usize> {
+        let word = self.bits.checked_shr(from as u32)?;
+        match word {
+            0 => None,
+            word => Some(from + word.trailing_zeros() as usize),
+        }
+    }
+
+    /// The largest occupied bucket before `to`.
+    fn last_before(&self, to: usize) -> Option<usize> {
+        let word = self.bits & ((1u32 << to) - 1) as u16;
+        match word {
+            0 => None,
+            word => Some(15 - word.leading_zeros() as usize),
+        }
+    }
+}
+
 impl<T> Container<T> for Nmap<T> {
     fn new() -> Nmap<T> {
         Nmap {
             buckets: [None, None, None, None, None, None, None, None, None, None],
+            bits: 0,
         }
     }
-    fn get(&self, k: u8) -> Option<NonNull<TrieNode<T>>> {
-        if k > 9 {
-            return None;
-        }
-        self.buckets[k as usize]
+    fn get(&self, k: u8) -> Link<T, Self> {
+        let i = Self::index(k)?;
+        self.buckets[i]
     }
 
-    fn set(&mut self, k: u8, v: NonNull<TrieNode<T>>) {
-        if k > 9 {
-            return;
-        }
-        unsafe {
-            if let Some(prev) = self.prev(k) {
-                (*prev.as_ptr()).next = Some(v);
-                (*v.as_ptr()).prev = Some(prev);
-            }
-            if let Some(next) = self.next(k) {
-                (*next.as_ptr()).prev = Some(v);
-                (*v.as_ptr()).next = Some(next);
-            }
-            self.buckets[k as usize] = Some(v);
+    fn set(&mut self, k: u8, v: NodeId<T, Self>) {
+        if let Some(i) = Self::index(k) {
+            self.buckets[i] = Some(v);
+            self.bits |= 1 << i;
         }
-        ()
     }
     fn del(&mut self, k: u8) -> bool {
-        if k > 9 {
-            return false;
-        }
-        unsafe {
-            if let Some(node) = self.buckets[k as usize] {
-                let prev = (*node.as_ptr()).prev;
-                let next = (*node.as_ptr()).next;
-                if let Some(p) = prev {
-                    (*p.as_ptr()).next = next
-                }
-                if let Some(n) = (*node.as_ptr()).next {
-                    (*n.as_ptr()).prev = prev
-                }
-                self.buckets[k as usize] = None;
-                return true;
+        match Self::index(k) {
+            Some(i) => {
+                self.bits &= !(1 << i);
+                self.buckets[i].take().is_some()
             }
+            None => false,
         }
-        false
     }
-    fn prev(&self, k: u8) -> Option<NonNull<TrieNode<T>>> {
-        if k > 10 {
-            return None;
-        }
-        let k = k - 1;
-        let mut i = k as i8;
-        while i >= 0 {
-            if !self.buckets[i as usize].is_none() {
-                return self.buckets[i as usize];
-            }
-            i -= 1;
-        }
-        None
+    fn prev(&self, k: u8) -> Link<T, Self> {
+        // a byte below '0' sorts before every bucket, one above '9' after them.
+        let to = (k.saturating_sub(b'0') as usize).min(10);
+        self.buckets[self.last_before(to)?]
     }
 
-    fn next(&self, k: u8) -> Option<NonNull<TrieNode<T>>> {
-        if k > 9 {
-            return None;
-        }
-        let k = (k + 1) as usize;
-        for i in k..10 {
-            if !self.buckets[i].is_none() {
-                return self.buckets[i];
-            }
-        }
-        None
+    fn next(&self, k: u8) -> Link<T, Self> {
+        let from = match k.checked_sub(b'0') {
+            Some(i) => i as usize + 1,
+            None => 0,
+        };
+        self.buckets[self.first_from(from)?]
     }
     fn is_head(&self, k: u8) -> bool {
-        if k > 9 {
-            return false;
-        }
-        let k = k as usize;
-        for i in 0..10 {
-            if !self.buckets[i].is_none() {
-                return i == k;
-            }
-        }
-        false
+        Self::index(k).is_some() && self.first_from(0) == Self::index(k)
     }
-    fn head(&self) -> Option<NonNull<TrieNode<T>>> {
-        for i in 0..10 {
-            if !self.buckets[i].is_none() {
-                return self.buckets[i];
-            }
-        }
-        None
+    fn head(&self) -> Link<T, Self> {
+        self.buckets[self.first_from(0)?]
     }
 
     fn is_tail(&self, k: u8) -> bool {
-        let k = k as i16;
-        let mut i = 9 as i16;
-        while i < k {
-            if !self.buckets[i as usize].is_none() {
-                return i == k;
-            }
-            i -= 1;
-        }
-        false
+        Self::index(k).is_some() && self.last_before(10) == Self::index(k)
     }
 
-    fn tail(&self) -> Option<NonNull<TrieNode<T>>> {
-        self.prev(10)
+    fn tail(&self) -> Link<T, Self> {
+        self.buckets[self.last_before(10)?]
     }
     fn keys(&self) -> Vec<u8> {
         let mut keys = Vec::with_capacity(10);
-        for i in 0..10 {
-            if let Some(_) = self.buckets[i] {
-                keys.push(i as u8)
-            }
+        let mut i = self.first_from(0);
+        while let Some(k) = i {
+            keys.push(b'0' + k as u8);
+            i = self.first_from(k + 1);
         }
-        return keys;
+        keys
     }
 
     fn pad() -> u8 {
-        return '0' as u8;
+        b'0'
+    }
+
+    fn valid(k: u8) -> bool {
+        k.is_ascii_digit()
+    }
+
+    fn capacity(&self) -> usize {
+        10
     }
 }
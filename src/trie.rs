@@ -1,368 +1,1451 @@
+use crate::arena::{Arena, Id};
+use crate::byte_map::ByteMap;
+use crate::cursor::{Cursor, CursorMut};
+use crate::entry::{Entry, OccupiedEntry, VacantEntry};
 use crate::error::{ErrorKind, TrieError};
-use crate::nmap::Nmap;
-use std::borrow::BorrowMut;
-use std::marker::PhantomData;
-use std::ptr::NonNull;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::iter::FusedIterator;
+use std::ops::{Bound, Index, RangeBounds};
 use std::{fmt, mem};
-pub trait Container<T> {
+
+/// Children of a `TrieNode`, indexed by one byte of the key.
+///
+/// `Nmap` stores ASCII decimal digits, `ByteMap` stores any byte. Containers
+/// only map key bytes to node handles: the nodes and the containers live in
+/// the arenas of their `Trie`, which also keeps the ordered `prev`/`next`
+/// chain of the nodes holding values.
+pub trait Container<T>: Sized {
     fn new() -> Self;
-    fn set(&mut self, k: u8, v: NonNull<TrieNode<T>>);
-    fn get(&self, k: u8) -> Option<NonNull<TrieNode<T>>>;
+    fn set(&mut self, k: u8, v: NodeId<T, Self>);
+    fn get(&self, k: u8) -> Link<T, Self>;
     fn del(&mut self, k: u8) -> bool;
-    fn prev(&self, k: u8) -> Option<NonNull<TrieNode<T>>>;
-    fn next(&self, k: u8) -> Option<NonNull<TrieNode<T>>>;
+    fn prev(&self, k: u8) -> Link<T, Self>;
+    fn next(&self, k: u8) -> Link<T, Self>;
     fn is_head(&self, k: u8) -> bool;
-    fn head(&self) -> Option<NonNull<TrieNode<T>>>;
+    fn head(&self) -> Link<T, Self>;
     fn is_tail(&self, k: u8) -> bool;
-    fn tail(&self) -> Option<NonNull<TrieNode<T>>>;
+    fn tail(&self) -> Link<T, Self>;
     fn keys(&self) -> Vec<u8>;
     fn pad() -> u8;
+    /// Whether `k` can be stored in this container.
+    fn valid(k: u8) -> bool;
+    /// The number of child slots, used or not.
+    fn capacity(&self) -> usize;
+    /// Bytes kept on the heap outside the container itself.
+    fn heap_bytes(&self) -> usize {
+        0
+    }
 }
 
-pub struct TrieNode<T> {
+/// A handle to a node in the arena of its `Trie`.
+pub type NodeId<T, C> = Id<TrieNode<T, C>>;
+
+/// A possibly empty handle to a node, as stored in containers and node links.
+pub type Link<T, C> = Option<NodeId<T, C>>;
+
+pub struct TrieNode<T, C> {
     pub key: Option<Vec<u8>>,
     pub node_key: u8,
+    /// Further bytes spelled by the edge into this node after `node_key`,
+    /// only ever non-empty in a trie with path compression.
+    pub segment: Vec<u8>,
     pub val: Option<T>,
-    pub prev: Option<NonNull<TrieNode<T>>>,
-    pub next: Option<NonNull<TrieNode<T>>>,
-    pub children: Option<NonNull<Nmap<T>>>,
-}
-
-// impl<T> Drop for TrieNode<T> {
-//     fn drop(&mut self) {
-//         self.children = None;
-//         self.prev = None;
-//         self.next = None;
-//         self.val = None;
-//         self.key = None;
-//     }
-// }
-
-#[derive(Debug, Clone)]
-pub struct Trie<T> {
+    pub prev: Link<T, C>,
+    pub next: Link<T, C>,
+    pub children: Option<Id<C>>,
+    /// Number of values stored in the subtree rooted at this node.
+    pub count: usize,
+}
+
+/// How a `Trie` constrains the length of its keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMode {
+    /// Every key is exactly `key_size` bytes and values only live on leaves.
+    Fixed,
+    /// Keys of any length coexist and interior nodes may carry a value.
+    Variable,
+    /// Keys up to `key_size` bytes are left-padded with the container's pad
+    /// byte, so `42` and `0042` name the same entry in a 4 digit `Nmap` trie.
+    Padded,
+}
+
+/// The shape and memory footprint of a `Trie`, as measured by `Trie::stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrieStats {
+    /// `nodes_by_depth[d]` is the number of nodes `d` edges below the root,
+    /// which is also the key length unless path compression is on.
+    pub nodes_by_depth: Vec<usize>,
+    /// Nodes without children.
+    pub leaves: usize,
+    /// Nodes with at least one child.
+    pub interior: usize,
+    /// The average number of children of the interior nodes at each depth,
+    /// zero where there are none.
+    pub fan_out_by_depth: Vec<f64>,
+    /// Child slots of every container, used or not.
+    pub slots: usize,
+    /// Child slots holding no node.
+    pub empty_slots: usize,
+    /// Estimated heap bytes of the nodes, containers and stored keys. Heap
+    /// memory owned by the values themselves is not counted.
+    pub heap_bytes: usize,
+}
+
+impl TrieStats {
+    pub fn nodes(&self) -> usize {
+        self.leaves + self.interior
+    }
+
+    /// The share of container slots holding no node, from 0 to 1.
+    pub fn empty_slot_ratio(&self) -> f64 {
+        if self.slots == 0 {
+            return 0.0;
+        }
+        self.empty_slots as f64 / self.slots as f64
+    }
+}
+
+/// A trie whose nodes and containers are kept in two arenas and refer to
+/// each other by handle. Removed nodes leave their slots to later inserts,
+/// and `clear` or drop release each arena in one go.
+#[derive(Debug)]
+pub struct Trie<T, C = ByteMap<T>> {
     key_size: usize,
-    root: Option<NonNull<TrieNode<T>>>,
-    head: Option<NonNull<TrieNode<T>>>,
-    tail: Option<NonNull<TrieNode<T>>>,
+    mode: KeyMode,
+    compressed: bool,
+    root: NodeId<T, C>,
+    pub(crate) head: Link<T, C>,
+    pub(crate) tail: Link<T, C>,
     size: usize,
-    marker: PhantomData<TrieNode<T>>,
+    pub(crate) nodes: Arena<TrieNode<T, C>>,
+    containers: Arena<C>,
 }
 
 #[must_use = "iterators are lazy and do nothing unless consumed"]
-pub struct Iter<'a, T: 'a> {
-    key_size: usize,
-    root: Option<NonNull<TrieNode<T>>>,
-    head: Option<NonNull<TrieNode<T>>>,
-    tail: Option<NonNull<TrieNode<T>>>,
+pub struct Iter<'a, T: 'a, C: 'a> {
+    nodes: &'a Arena<TrieNode<T, C>>,
+    head: Link<T, C>,
+    tail: Link<T, C>,
     size: usize,
-    marker: PhantomData<&'a TrieNode<T>>,
 }
 
-impl<T: fmt::Debug> fmt::Debug for Iter<'_, T> {
+impl<T: fmt::Debug, C> fmt::Debug for Iter<'_, T, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("Iter")
-            .field(&*mem::ManuallyDrop::new(Trie {
-                key_size: self.key_size,
-                root: self.root,
-                head: self.head,
-                tail: self.tail,
-                size: self.size,
-                marker: PhantomData,
-            }))
-            .field(&self.size)
-            .finish()
+        f.debug_list().entries(self.clone()).finish()
     }
 }
 
-impl<T> Clone for Iter<'_, T> {
+impl<T, C> Clone for Iter<'_, T, C> {
     fn clone(&self) -> Self {
         Iter { ..*self }
     }
 }
 
-pub struct IterMut<'a, T: 'a> {
-    key_size: usize,
-    root: Option<NonNull<TrieNode<T>>>,
-    head: Option<NonNull<TrieNode<T>>>,
-    tail: Option<NonNull<TrieNode<T>>>,
+pub struct IterMut<'a, T: 'a, C: 'a> {
+    nodes: &'a mut Arena<TrieNode<T, C>>,
+    head: Link<T, C>,
+    tail: Link<T, C>,
     size: usize,
-    marker: PhantomData<&'a mut TrieNode<T>>,
 }
 
-impl<T: fmt::Debug> fmt::Debug for IterMut<'_, T> {
+/// Lists the entries not yet yielded. Values already handed out may still be
+/// borrowed mutably, so each remaining node is read on its own rather than
+/// through a borrow of the whole arena.
+impl<T: fmt::Debug, C> fmt::Debug for IterMut<'_, T, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("IterMut")
-            .field(&*mem::ManuallyDrop::new(Trie {
-                key_size: self.key_size,
-                root: self.root,
-                head: self.head,
-                tail: self.tail,
-                size: self.size,
-                marker: PhantomData,
-            }))
-            .field(&self.size)
-            .finish()
+        let mut list = f.debug_list();
+        let mut cur = self.head;
+        for _ in 0..self.size {
+            let node = match cur.and_then(|id| self.nodes.get_ptr(id)) {
+                Some(node) => node,
+                None => break,
+            };
+            // no reference to a node that was not yielded yet exists.
+            let node = unsafe { &*node };
+            if let (Some(key), Some(val)) = (node.key.as_deref(), node.val.as_ref()) {
+                list.entry(&(key, val));
+            }
+            cur = node.next;
+        }
+        list.finish()
     }
 }
 
-#[derive(Clone)]
-pub struct IntoIter<T> {
-    list: Trie<T>,
+pub struct IntoIter<T, C> {
+    list: Trie<T, C>,
 }
 
-impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
+impl<T: fmt::Debug, C: fmt::Debug> fmt::Debug for IntoIter<T, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("IntoIter").field(&self.list).finish()
     }
 }
 
-impl<T> TrieNode<T>
+/// Rebuilds the node graph of `self` into fresh arenas, leaving out the
+/// slots freed by removals.
+impl<T, C> Clone for Trie<T, C>
 where
     T: Clone,
+    C: Container<T>,
 {
-    pub fn root() -> TrieNode<T> {
-        TrieNode {
-            node_key: 0,
-            key: None,
-            val: None,
-            prev: None,
-            next: None,
-            children: Some(Box::leak(Box::new(Nmap::<T>::new())).into()),
+    fn clone(&self) -> Self {
+        let mut trie = Trie::with_mode(self.key_size, self.mode);
+        trie.compressed = self.compressed;
+        // a pre-order walk visiting children in key order meets the values
+        // in key order, so each one is appended to the chain.
+        let mut stack = vec![(self.root, trie.root)];
+        while let Some((src, dst)) = stack.pop() {
+            let node = &self.nodes[src];
+            trie.nodes[dst].count = node.count;
+            if let Some(val) = node.val.as_ref() {
+                trie.nodes[dst].key = node.key.clone();
+                trie.nodes[dst].val = Some(val.clone());
+                trie.link(dst, None);
+            }
+            let children = match self.children(src) {
+                Some(children) => children,
+                None => continue,
+            };
+            let start = stack.len();
+            for k in children.keys() {
+                if let Some(child) = children.get(k) {
+                    let copy = trie.new_child(dst, k);
+                    trie.nodes[copy].segment = self.nodes[child].segment.clone();
+                    stack.push((child, copy));
+                }
+            }
+            stack[start..].reverse();
+        }
+        trie.size = self.size;
+        trie
+    }
+}
+
+/// Builds a fixed-width trie whose key size is the length of the first key,
+/// or a variable-length one from an empty iterator.
+///
+/// # Panics
+///
+/// Panics if a later key does not fit the trie, as `insert` does.
+impl<T, C> FromIterator<(Vec<u8>, T)> for Trie<T, C>
+where
+    C: Container<T>,
+{
+    fn from_iter<I: IntoIterator<Item = (Vec<u8>, T)>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let first = match iter.next() {
+            Some(first) => first,
+            None => return Trie::variable(),
+        };
+        let mut trie = Trie::new(first.0.len());
+        trie.extend(std::iter::once(first).chain(iter));
+        trie
+    }
+}
+
+/// Inserts every pair, replacing existing values.
+///
+/// # Panics
+///
+/// Panics if a key does not fit the trie, as `insert` does.
+impl<T, C> Extend<(Vec<u8>, T)> for Trie<T, C>
+where
+    C: Container<T>,
+{
+    fn extend<I: IntoIterator<Item = (Vec<u8>, T)>>(&mut self, iter: I) {
+        for (key, val) in iter {
+            self.insert(key, val);
         }
     }
-    pub fn new(k: u8) -> TrieNode<T> {
+}
+
+/// # Panics
+///
+/// Panics if the key is not present in the trie.
+impl<T, C, K> Index<&K> for Trie<T, C>
+where
+    C: Container<T>,
+    K: AsRef<[u8]> + ?Sized,
+{
+    type Output = T;
+
+    fn index(&self, key: &K) -> &T {
+        self.get(key.as_ref()).expect("no entry found for key")
+    }
+}
+
+pub struct Range<'a, T: 'a, C: 'a> {
+    nodes: &'a Arena<TrieNode<T, C>>,
+    front: Link<T, C>,
+    back: Link<T, C>,
+}
+
+impl<T, C> Clone for Range<'_, T, C> {
+    fn clone(&self) -> Self {
+        Range { ..*self }
+    }
+}
+
+impl<T, C> TrieNode<T, C> {
+    pub fn root() -> TrieNode<T, C> {
+        TrieNode::new(0)
+    }
+    pub fn new(k: u8) -> TrieNode<T, C> {
         TrieNode {
             node_key: k,
+            segment: Vec::new(),
             key: None,
             val: None,
             prev: None,
             next: None,
-            children: Some(Box::leak(Box::new(Nmap::<T>::new())).into()),
+            children: None,
+            count: 0,
         }
     }
-    pub fn leaf(k: u8, key: Vec<u8>, val: T) -> TrieNode<T> {
+    pub fn leaf(k: u8, key: Vec<u8>, val: T) -> TrieNode<T, C> {
         TrieNode {
             node_key: k,
+            segment: Vec::new(),
             key: Some(key),
             val: Some(val),
             prev: None,
             next: None,
             children: None,
+            count: 1,
         }
     }
 
-    pub fn into_val(self: Box<Self>) -> Option<T> {
-        self.val
+    pub fn into_val(mut self) -> Option<T> {
+        self.val.take()
     }
 }
 
-impl<T> Trie<T>
+impl<T, C> Trie<T, C>
 where
-    T: Clone,
+    C: Container<T>,
 {
-    pub fn new(key_size: usize) -> Trie<T> {
+    /// Creates a trie whose keys are all exactly `key_size` bytes.
+    pub fn new(key_size: usize) -> Trie<T, C> {
+        Trie::with_mode(key_size, KeyMode::Fixed)
+    }
+
+    /// Builds a trie of `key_size` byte keys from strictly increasing input in
+    /// one pass: only the path below the prefix shared with the previous key
+    /// is created and every value is appended to the end of the leaf chain.
+    pub fn from_sorted_iter<I>(key_size: usize, iter: I) -> Result<Trie<T, C>, TrieError>
+    where
+        I: IntoIterator<Item = (Vec<u8>, T)>,
+    {
+        let mut trie = Trie::new(key_size);
+        // path[i] is the node at depth i on the previous key's path.
+        let mut path = vec![trie.root];
+        for (key, val) in iter {
+            trie.check_key(&key)?;
+            let common = match trie.tail {
+                Some(tail) => {
+                    let prev = trie.nodes[tail].key.as_deref().unwrap_or_default();
+                    match prev.cmp(&key) {
+                        Ordering::Less => {}
+                        Ordering::Equal => return Err(TrieError::from(ErrorKind::KeyDuplicated)),
+                        Ordering::Greater => return Err(TrieError::from(ErrorKind::KeyNotSorted)),
+                    }
+                    Self::common_len(prev, &key)
+                }
+                None => 0,
+            };
+            path.truncate(common + 1);
+            let mut node = path[common];
+            for &k in key[common..].iter() {
+                node = trie.new_child(node, k);
+                path.push(node);
+            }
+            trie.nodes[node].key = Some(key);
+            trie.nodes[node].val = Some(val);
+            trie.link(node, None);
+            for &node in path.iter() {
+                trie.nodes[node].count += 1;
+            }
+            trie.size += 1;
+        }
+        Ok(trie)
+    }
+
+    /// Creates a trie accepting keys of any length.
+    pub fn variable() -> Trie<T, C> {
+        Trie::with_mode(0, KeyMode::Variable)
+    }
+
+    /// Creates a trie of `key_size` byte keys that pads shorter keys with
+    /// `Container::pad()`.
+    pub fn padded(key_size: usize) -> Trie<T, C> {
+        Trie::with_mode(key_size, KeyMode::Padded)
+    }
+
+    fn with_mode(key_size: usize, mode: KeyMode) -> Trie<T, C> {
+        let mut nodes = Arena::new();
+        let root = nodes.insert(TrieNode::root());
         Trie {
             key_size,
-            root: Some(Box::leak(Box::new(TrieNode::root())).into()),
+            mode,
+            compressed: false,
+            root,
             head: None,
             tail: None,
             size: 0,
-            marker: PhantomData,
+            nodes,
+            containers: Arena::new(),
+        }
+    }
+
+    pub fn mode(&self) -> KeyMode {
+        self.mode
+    }
+
+    /// Turns on path compression: a chain of single-child nodes is stored as
+    /// one node whose edge spells several key bytes, and is split when a key
+    /// diverges inside it. Lookups then visit one node per branching point
+    /// rather than per key byte. Paths created before it is turned on are
+    /// left as they are.
+    pub fn with_path_compression(mut self) -> Trie<T, C> {
+        self.compressed = true;
+        self
+    }
+
+    pub fn path_compression(&self) -> bool {
+        self.compressed
+    }
+
+    /// Brings `key` to the form stored in the trie and returns it along with
+    /// its original length. Only `KeyMode::Padded` ever changes the key.
+    pub fn normalize<'k>(&self, key: &'k [u8]) -> Result<(Cow<'k, [u8]>, usize), TrieError> {
+        let len = key.len();
+        let key = match self.mode {
+            KeyMode::Padded if len < self.key_size => {
+                let mut padded = vec![C::pad(); self.key_size - len];
+                padded.extend_from_slice(key);
+                Cow::Owned(padded)
+            }
+            _ => Cow::Borrowed(key),
+        };
+        self.check_key(&key)?;
+        Ok((key, len))
+    }
+
+    /// Normalizes a key used only for searching, keeping it as is when it
+    /// could not be stored.
+    pub(crate) fn probe<'k>(&self, key: &'k [u8]) -> Cow<'k, [u8]> {
+        match self.normalize(key) {
+            Ok((key, _)) => key,
+            Err(_) => Cow::Borrowed(key),
         }
     }
 
-    pub fn set(&mut self, key: Vec<u8>, val: T) -> Result<(), TrieError> {
-        if key.clone().len() != self.key_size {
+    fn check_key(&self, key: &[u8]) -> Result<(), TrieError> {
+        if self.mode != KeyMode::Variable && key.len() != self.key_size {
             return Err(TrieError::from(ErrorKind::KeySizeNotMatch));
         }
+        if !key.iter().all(|k| C::valid(*k)) {
+            return Err(TrieError::from(ErrorKind::InvalidKey));
+        }
+        Ok(())
+    }
 
-        let endk = key.clone()[self.key_size - 1];
-
-        unsafe {
-            let mut cur = self.root;
-            for k in key.clone() {
-                if let Some(cur_ptr) = cur {
-                    if let Some(children_ptr) = (*cur_ptr.as_ptr()).children {
-                        let children = (*children_ptr.as_ptr()).borrow_mut();
-                        let node = children.get(k);
-                        if node.is_none() {
-                            let node_ptr;
-                            if k == endk {
-                                node_ptr = Box::leak(Box::new(TrieNode::leaf(
-                                    k,
-                                    key.clone(),
-                                    val.clone(),
-                                )))
-                                .into();
-                            } else {
-                                node_ptr = Box::leak(Box::new(TrieNode::<T>::new(k))).into();
-                            }
-                            children.set(k, node_ptr);
-                            if children.is_tail(k) {
-                                if let Some(next) = (*cur_ptr.as_ptr()).next {
-                                    if let Some(next_children) = (*next.as_ptr()).children {
-                                        if let Some(next_head) = (*next_children.as_ptr()).head() {
-                                            (*next_head.as_ptr()).prev = Some(node_ptr);
-                                            (*node_ptr.as_ptr()).next = Some(next_head);
-                                        }
-                                    }
-                                }
-                            }
-                            if children.is_head(k) {
-                                if let Some(prev) = (*cur_ptr.as_ptr()).prev {
-                                    if let Some(prev_children) = (*prev.as_ptr()).children {
-                                        if let Some(prev_tail) = (*prev_children.as_ptr()).tail() {
-                                            (*prev_tail.as_ptr()).next = Some(node_ptr);
-                                            (*node_ptr.as_ptr()).prev = Some(prev);
-                                        }
-                                    }
-                                }
-                            }
-                            cur = Some(node_ptr);
-                        } else {
-                            cur = node;
-                        }
-                        continue;
-                    }
-                    return Err(TrieError::from(ErrorKind::Unexpect));
+    /// Stores `val` under `key`, returning the value it replaced.
+    pub fn set(&mut self, key: Vec<u8>, val: T) -> Result<Option<T>, TrieError> {
+        let padded = match self.normalize(&key)?.0 {
+            Cow::Owned(padded) => Some(padded),
+            Cow::Borrowed(_) => None,
+        };
+        let key = padded.unwrap_or(key);
+
+        let mut path = Vec::with_capacity(key.len());
+        let node = self.make_path(self.root, &key, &mut path);
+        Ok(self.fill(&path, node, key, val))
+    }
+
+    /// Like `set`, for callers that know their keys fit the trie.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is rejected by `normalize`.
+    pub fn insert(&mut self, key: Vec<u8>, val: T) -> Option<T> {
+        self.set(key, val).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Gets the entry for `key` with a single descent, for in-place updates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is rejected by `normalize`.
+    pub fn entry(&mut self, key: Vec<u8>) -> Entry<'_, T, C> {
+        let padded = match self.normalize(&key) {
+            Ok((Cow::Owned(padded), _)) => Some(padded),
+            Ok((Cow::Borrowed(_), _)) => None,
+            Err(err) => panic!("{}", err),
+        };
+        let key = padded.unwrap_or(key);
+
+        let mut path = Vec::with_capacity(key.len());
+        let mut node = self.root;
+        let mut depth = 0;
+        while depth < key.len() {
+            match self.child_on(node, &key[depth..]) {
+                Some((child, len)) => {
+                    path.push(node);
+                    node = child;
+                    depth += len;
                 }
-                return Err(TrieError::from(ErrorKind::Unexpect));
+                None => break,
             }
+        }
+        if depth == key.len() && self.nodes[node].val.is_some() {
+            return Entry::Occupied(OccupiedEntry {
+                trie: self,
+                path,
+                node,
+            });
+        }
+        Entry::Vacant(VacantEntry {
+            trie: self,
+            key,
+            path,
+            node,
+            depth,
+        })
+    }
+
+    /// Creates the missing nodes on the path `key` below `cur` and returns the
+    /// last one. `path` holds the ancestors of `cur` and is extended to those
+    /// of the returned node, for `fill` to count the new value on.
+    pub(crate) fn make_path(
+        &mut self,
+        mut cur: NodeId<T, C>,
+        key: &[u8],
+        path: &mut Vec<NodeId<T, C>>,
+    ) -> NodeId<T, C> {
+        let mut depth = 0;
+        while depth < key.len() {
+            let k = key[depth];
+            path.push(cur);
+            let child = match self.children(cur).and_then(|c| c.get(k)) {
+                Some(child) => child,
+                None if self.compressed => {
+                    let child = self.new_child(cur, k);
+                    self.nodes[child].segment = key[depth + 1..].to_vec();
+                    return child;
+                }
+                None => {
+                    cur = self.new_child(cur, k);
+                    depth += 1;
+                    continue;
+                }
+            };
+            let segment = &self.nodes[child].segment;
+            let common = Self::common_len(segment, &key[depth + 1..]);
+            cur = if common < segment.len() {
+                self.split(cur, child, common)
+            } else {
+                child
+            };
+            depth += 1 + common;
+        }
+        cur
+    }
+
+    /// Cuts the edge into `child` after the first `at` bytes of its segment,
+    /// putting a new node there, and returns that node.
+    fn split(&mut self, parent: NodeId<T, C>, child: NodeId<T, C>, at: usize) -> NodeId<T, C> {
+        let node = &mut self.nodes[child];
+        let mut mid = TrieNode::new(node.node_key);
+        let mut tail = node.segment.split_off(at);
+        mid.segment = mem::take(&mut node.segment);
+        mid.count = node.count;
+        node.node_key = tail.remove(0);
+        node.segment = tail;
+
+        let mut children = C::new();
+        children.set(node.node_key, child);
+        mid.children = Some(self.containers.insert(children));
+        let node_key = mid.node_key;
+        let mid = self.nodes.insert(mid);
+        if let Some(siblings) = self.nodes[parent].children {
+            self.containers[siblings].set(node_key, mid);
+        }
+        mid
+    }
+
+    /// Folds `node`, left without a value and with a single child, into that
+    /// child, undoing a `split`.
+    fn merge(&mut self, parent: NodeId<T, C>, node: NodeId<T, C>) {
+        if self.nodes[node].val.is_some() {
+            return;
+        }
+        let children = match self.nodes[node].children {
+            Some(children) => children,
+            None => return,
+        };
+        let child = match (
+            self.containers[children].head(),
+            self.containers[children].tail(),
+        ) {
+            (Some(head), Some(tail)) if head == tail => head,
+            _ => return,
+        };
+        self.containers.remove(children);
+        let removed = self.nodes.remove(node);
+        let node = &mut self.nodes[child];
+        let mut segment = removed.segment;
+        segment.push(node.node_key);
+        segment.append(&mut node.segment);
+        node.segment = segment;
+        node.node_key = removed.node_key;
+        if let Some(siblings) = self.nodes[parent].children {
+            self.containers[siblings].set(removed.node_key, child);
+        }
+    }
+
+    /// The child of `node` whose whole edge spells the start of `key`, along
+    /// with the length of that edge.
+    fn child_on(&self, node: NodeId<T, C>, key: &[u8]) -> Option<(NodeId<T, C>, usize)> {
+        let k = *key.first()?;
+        let child = self.children(node)?.get(k)?;
+        let segment = &self.nodes[child].segment;
+        if segment.is_empty() || key[1..].starts_with(segment) {
+            Some((child, 1 + segment.len()))
+        } else {
+            None
+        }
+    }
+
+    fn common_len(a: &[u8], b: &[u8]) -> usize {
+        let mut i = 0;
+        while i < a.len() && i < b.len() && a[i] == b[i] {
+            i += 1;
+        }
+        i
+    }
 
-            if self.head.is_none() {
-                self.head = cur;
-                self.tail = cur;
-                return Ok(());
+    /// The container of the children of `node`, if it ever had any.
+    fn children(&self, node: NodeId<T, C>) -> Option<&C> {
+        Some(&self.containers[self.nodes[node].children?])
+    }
+
+    /// Creates the child `k` of `parent`, which must not exist yet.
+    fn new_child(&mut self, parent: NodeId<T, C>, k: u8) -> NodeId<T, C> {
+        let children = match self.nodes[parent].children {
+            Some(children) => children,
+            None => {
+                let children = self.containers.insert(C::new());
+                self.nodes[parent].children = Some(children);
+                children
             }
+        };
+        let node = self.nodes.insert(TrieNode::new(k));
+        self.containers[children].set(k, node);
+        node
+    }
 
-            if let Some(head) = self.head {
-                if !(*head.as_ptr()).prev.is_none() {
-                    if (*head.as_ptr()).prev == cur {
-                        self.head = cur;
-                    }
+    /// Puts `val` on `node`, the end of the path `key` below the ancestors in
+    /// `path`, linking the node into the leaf chain if it held no value yet.
+    pub(crate) fn fill(
+        &mut self,
+        path: &[NodeId<T, C>],
+        node: NodeId<T, C>,
+        key: Vec<u8>,
+        val: T,
+    ) -> Option<T> {
+        if let Some(old) = self.nodes[node].val.as_mut() {
+            return Some(mem::replace(old, val));
+        }
+        // appending past the tail is the common case for monotonic keys.
+        // Otherwise the next value is the first one below `node`, or past
+        // its subtree, found from `path` rather than a fresh descent.
+        let next = match self.tail {
+            Some(tail) if self.nodes[tail].key.as_deref() < Some(&key[..]) => None,
+            _ => self.first(node).or_else(|| self.after(path, node)),
+        };
+        // every node on the path, the new one included, gains a value.
+        for &ancestor in path {
+            self.nodes[ancestor].count += 1;
+        }
+        let filled = &mut self.nodes[node];
+        filled.count += 1;
+        filled.key = Some(key);
+        filled.val = Some(val);
+        self.link(node, next);
+        self.size += 1;
+        None
+    }
+
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&T> {
+        let (key, _) = self.normalize(key.as_ref()).ok()?;
+        let node = self.find(&key)?;
+        self.nodes[node].val.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<&mut T> {
+        let (key, _) = self.normalize(key.as_ref()).ok()?;
+        let node = self.find(&key)?;
+        self.nodes[node].val.as_mut()
+    }
+
+    pub fn contains_key(&self, key: impl AsRef<[u8]>) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn del(&mut self, key: impl AsRef<[u8]>) -> bool {
+        self.remove(key).is_some()
+    }
+
+    /// Removes `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<T> {
+        let (key, _) = self.normalize(key.as_ref()).ok()?;
+        let mut stack = Vec::<NodeId<T, C>>::with_capacity(key.len());
+
+        let mut cur = self.root;
+        let mut depth = 0;
+        while depth < key.len() {
+            let (node, len) = self.child_on(cur, &key[depth..])?;
+            stack.push(cur);
+            cur = node;
+            depth += len;
+        }
+        self.remove_at(stack, cur)
+    }
+
+    /// Takes the value off `node`, whose ancestors are `path`, and frees what
+    /// is left unused.
+    pub(crate) fn remove_at(&mut self, path: Vec<NodeId<T, C>>, node: NodeId<T, C>) -> Option<T> {
+        let val = self.nodes[node].val.take()?;
+        self.unlink(node);
+        self.nodes[node].key = None;
+        self.size -= 1;
+        self.nodes[node].count -= 1;
+        for &ancestor in path.iter() {
+            self.nodes[ancestor].count -= 1;
+        }
+
+        self.prune(path, node);
+        Some(val)
+    }
+
+    /// Frees `node`, then each ancestor in `stack` from the bottom up, as long
+    /// as they are left without children or value. A container is freed along
+    /// with its last child, so a node without one has no children.
+    fn prune(&mut self, mut stack: Vec<NodeId<T, C>>, mut node: NodeId<T, C>) {
+        while let Some(parent) = stack.pop() {
+            if self.nodes[node].val.is_some() || self.nodes[node].children.is_some() {
+                if self.compressed {
+                    self.merge(parent, node);
                 }
+                break;
             }
+            let removed = self.nodes.remove(node);
+            if let Some(siblings) = self.nodes[parent].children {
+                self.containers[siblings].del(removed.node_key);
+                if self.containers[siblings].head().is_none() {
+                    self.containers.remove(siblings);
+                    self.nodes[parent].children = None;
+                }
+            }
+            node = parent;
+        }
+    }
 
-            if let Some(tail) = self.tail {
-                if !(*tail.as_ptr()).next.is_none() {
-                    if Some((*tail.as_ptr()).next) == Some(cur) {
-                        self.tail = cur;
-                    }
+    pub fn gt(&self, key: impl AsRef<[u8]>) -> Option<&T> {
+        self._gt(key, false)
+    }
+
+    pub fn gte(&self, key: impl AsRef<[u8]>) -> Option<&T> {
+        self._gt(key, true)
+    }
+
+    pub fn _gt(&self, key: impl AsRef<[u8]>, eq: bool) -> Option<&T> {
+        let key = self.probe(key.as_ref());
+        let node = self.lower_bound(&key, eq)?;
+        self.nodes[node].val.as_ref()
+    }
+
+    /// Deletes every key starting with `prefix`, returning how many were removed.
+    ///
+    /// The subtree covering `prefix` is cut off whole: its run of the leaf
+    /// chain is spliced out in one go and its nodes are freed without a
+    /// descent from the root per key.
+    pub fn remove_prefix(&mut self, prefix: impl AsRef<[u8]>) -> usize {
+        let mut stack = Vec::new();
+        let node = match self.find_prefix(prefix.as_ref(), &mut stack) {
+            Some(node) => node,
+            None => return 0,
+        };
+        let (first, last) = match (self.first(node), self.last(node)) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0,
+        };
+        let prev = self.nodes[first].prev;
+        let next = self.nodes[last].next;
+        match prev {
+            Some(prev) => self.nodes[prev].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+        let removed = self.nodes[node].count;
+        for &ancestor in stack.iter() {
+            self.nodes[ancestor].count -= removed;
+        }
+        self.size -= removed;
+
+        // free everything below `node`, then prune `node` like a removed leaf.
+        let mut below: Vec<Id<C>> = self.nodes[node].children.take().into_iter().collect();
+        while let Some(children) = below.pop() {
+            let children = self.containers.remove(children);
+            for k in children.keys() {
+                if let Some(child) = children.get(k) {
+                    below.extend(self.nodes.remove(child).children);
                 }
             }
         }
-        Ok(())
+        let cut = &mut self.nodes[node];
+        cut.key = None;
+        cut.val = None;
+        cut.prev = None;
+        cut.next = None;
+        cut.count = 0;
+        self.prune(stack, node);
+        removed
     }
 
-    pub fn get(&self, key: Vec<u8>) -> Result<T, TrieError> {
-        if key.len() != self.key_size {
-            return Err(TrieError::from(ErrorKind::KeySizeNotMatch));
+    pub fn lt(&self, key: impl AsRef<[u8]>) -> Option<&T> {
+        self._lt(key, false)
+    }
+
+    pub fn lte(&self, key: impl AsRef<[u8]>) -> Option<&T> {
+        self._lt(key, true)
+    }
+
+    pub fn _lt(&self, key: impl AsRef<[u8]>, eq: bool) -> Option<&T> {
+        let key = self.probe(key.as_ref());
+        let node = self.upper_bound(&key, eq)?;
+        self.nodes[node].val.as_ref()
+    }
+
+    /// A cursor on the entry with the smallest key.
+    pub fn cursor(&self) -> Cursor<'_, T, C> {
+        Cursor {
+            current: self.head,
+            trie: self,
         }
-        unsafe {
-            let mut cur = self.root;
-            for k in key {
-                if let Some(cur_ptr) = cur {
-                    if let Some(children) = (*cur_ptr.as_ptr()).children {
-                        let node = (*children.as_ptr()).get(k);
-                        if let Some(node_v) = node {
-                            if let Some(val) = (*node_v.as_ptr()).val.clone() {
-                                return Ok(val);
-                            }
-                        }
-                        cur = node;
-                        continue;
-                    }
+    }
+
+    /// A cursor on the entry with the smallest key that can modify the trie.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, C> {
+        CursorMut {
+            current: self.head,
+            trie: self,
+        }
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&[u8], &T)> {
+        self.iter().next()
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&[u8], &T)> {
+        self.iter().next_back()
+    }
+
+    /// Removes and returns the entry with the smallest key.
+    pub fn pop_first(&mut self) -> Option<(Vec<u8>, T)> {
+        let key = self.nodes[self.head?].key.clone()?;
+        let val = self.remove(&key)?;
+        Some((key, val))
+    }
+
+    /// Removes and returns the entry with the largest key.
+    pub fn pop_last(&mut self) -> Option<(Vec<u8>, T)> {
+        let key = self.nodes[self.tail?].key.clone()?;
+        let val = self.remove(&key)?;
+        Some((key, val))
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Removes every entry, emptying both arenas at once rather than freeing
+    /// node by node.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.containers.clear();
+        self.root = self.nodes.insert(TrieNode::root());
+        self.head = None;
+        self.tail = None;
+        self.size = 0;
+    }
+
+    /// Iterates over `(key, &value)` pairs in key order.
+    pub fn iter(&self) -> Iter<'_, T, C> {
+        Iter {
+            nodes: &self.nodes,
+            head: self.head,
+            tail: self.tail,
+            size: self.size,
+        }
+    }
+
+    /// Iterates over `(key, &mut value)` pairs in key order.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, C> {
+        IterMut {
+            nodes: &mut self.nodes,
+            head: self.head,
+            tail: self.tail,
+            size: self.size,
+        }
+    }
+
+    /// Iterates over the `(key, &value)` pairs whose key falls in `range`, in
+    /// key order. An inverted range yields nothing.
+    pub fn range<K, R>(&self, range: R) -> Range<'_, T, C>
+    where
+        K: AsRef<[u8]> + ?Sized,
+        R: RangeBounds<K>,
+    {
+        let front = match range.start_bound() {
+            Bound::Included(k) => self.lower_bound(&self.probe(k.as_ref()), true),
+            Bound::Excluded(k) => self.lower_bound(&self.probe(k.as_ref()), false),
+            Bound::Unbounded => self.head,
+        };
+        let back = match range.end_bound() {
+            Bound::Included(k) => self.upper_bound(&self.probe(k.as_ref()), true),
+            Bound::Excluded(k) => self.upper_bound(&self.probe(k.as_ref()), false),
+            Bound::Unbounded => self.tail,
+        };
+        let (front, back) = match (front, back) {
+            (Some(f), Some(b)) if self.nodes[f].key <= self.nodes[b].key => (front, back),
+            _ => (None, None),
+        };
+        Range {
+            nodes: &self.nodes,
+            front,
+            back,
+        }
+    }
+
+    /// Iterates over the `(key, &value)` pairs whose key starts with `prefix`,
+    /// in key order.
+    pub fn prefix_iter(&self, prefix: impl AsRef<[u8]>) -> Range<'_, T, C> {
+        let (front, back) = match self.find_prefix(prefix.as_ref(), &mut Vec::new()) {
+            Some(node) => (self.first(node), self.last(node)),
+            None => (None, None),
+        };
+        Range {
+            nodes: &self.nodes,
+            front,
+            back,
+        }
+    }
+
+    /// Counts the keys starting with `prefix`.
+    pub fn prefix_count(&self, prefix: impl AsRef<[u8]>) -> usize {
+        match self.find_prefix(prefix.as_ref(), &mut Vec::new()) {
+            Some(node) => self.nodes[node].count,
+            None => 0,
+        }
+    }
+
+    /// The entry with the longest key that is a prefix of `key`, such as the
+    /// most specific route for an address or dial code for a number.
+    pub fn longest_prefix_match(&self, key: impl AsRef<[u8]>) -> Option<(&[u8], &T)> {
+        let key = key.as_ref();
+        let mut best = None;
+        let mut cur = self.root;
+        let mut depth = 0;
+        loop {
+            if self.nodes[cur].val.is_some() {
+                best = Some(cur);
+            }
+            match self.child_on(cur, &key[depth..]) {
+                Some((child, len)) => {
+                    cur = child;
+                    depth += len;
+                }
+                None => break,
+            }
+        }
+        let node = &self.nodes[best?];
+        Some((node.key.as_deref().unwrap_or_default(), node.val.as_ref()?))
+    }
+
+    /// Counts the keys less than `key`.
+    pub fn rank(&self, key: impl AsRef<[u8]>) -> usize {
+        let key = self.probe(key.as_ref());
+        self.count_below(&key, false)
+    }
+
+    /// The entry with the `i`-th smallest key, counting from zero.
+    pub fn select(&self, mut i: usize) -> Option<(&[u8], &T)> {
+        if i >= self.size {
+            return None;
+        }
+        let mut cur = self.root;
+        loop {
+            let node = &self.nodes[cur];
+            if let Some(val) = node.val.as_ref() {
+                if i == 0 {
+                    return Some((node.key.as_deref().unwrap_or_default(), val));
+                }
+                i -= 1;
+            }
+            // one ordered pass over the children, rather than a `next` call
+            // per sibling, which is a slot scan in the larger containers.
+            let children = self.children(cur)?;
+            let mut below = None;
+            for k in children.keys() {
+                let node = children.get(k)?;
+                let count = self.nodes[node].count;
+                if i < count {
+                    below = Some(node);
                     break;
                 }
+                i -= count;
             }
+            cur = below?;
         }
-        return Err(TrieError::from(ErrorKind::Notfound));
-    }
-
-    pub fn del(&mut self, key: Vec<u8>) -> bool {
-        if self.key_size != key.len() {
-            return false;
-        }
-        let mut stack = std::collections::LinkedList::<NonNull<TrieNode<T>>>::new();
-
-        unsafe {
-            let mut cur = self.root;
-            for k in key {
-                if let Some(cur_ptr) = cur {
-                    if let Some(children) = (*cur_ptr.as_ptr()).children {
-                        let node = (*children.as_ptr()).get(k);
-                        if let Some(node_v) = node {
-                            if !(*node_v.as_ptr()).val.is_none() {
-                                if let Some(n) = stack.pop_back() {
-                                    if let Some(children) = (*n.as_ptr()).children {
-                                        (*children.as_ptr()).del(k);
-                                    }
-                                    stack.push_back(n);
-                                }
-                            } else {
-                                stack.push_back(node_v);
-                            }
-                        }
-                        cur = node;
-                        continue;
-                    }
-                    return false;
+    }
+
+    /// Counts the keys that fall in `range`, as `range(range).count()` would
+    /// without walking the entries.
+    pub fn count_range<K, R>(&self, range: R) -> usize
+    where
+        K: AsRef<[u8]> + ?Sized,
+        R: RangeBounds<K>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(k) => self.count_below(&self.probe(k.as_ref()), false),
+            Bound::Excluded(k) => self.count_below(&self.probe(k.as_ref()), true),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(k) => self.count_below(&self.probe(k.as_ref()), true),
+            Bound::Excluded(k) => self.count_below(&self.probe(k.as_ref()), false),
+            Bound::Unbounded => self.size,
+        };
+        end.saturating_sub(start)
+    }
+
+    /// Walks every node to measure the shape of the trie and estimate its heap
+    /// usage.
+    pub fn stats(&self) -> TrieStats {
+        let mut stats = TrieStats {
+            heap_bytes: self.nodes.heap_bytes() + self.containers.heap_bytes(),
+            ..TrieStats::default()
+        };
+        let mut children_by_depth = Vec::new();
+        let mut interior_by_depth = Vec::new();
+        let mut stack = vec![(self.root, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            if stats.nodes_by_depth.len() == depth {
+                stats.nodes_by_depth.push(0);
+                children_by_depth.push(0);
+                interior_by_depth.push(0);
+            }
+            stats.nodes_by_depth[depth] += 1;
+            let node = &self.nodes[id];
+            stats.heap_bytes += node.key.as_ref().map_or(0, |k| k.capacity());
+            stats.heap_bytes += node.segment.capacity();
+
+            let children = match self.children(id) {
+                Some(children) => children,
+                None => {
+                    stats.leaves += 1;
+                    continue;
                 }
-                return false;
+            };
+            let keys = children.keys();
+            stats.slots += children.capacity();
+            stats.empty_slots += children.capacity() - keys.len();
+            stats.heap_bytes += children.heap_bytes();
+            stats.interior += 1;
+            interior_by_depth[depth] += 1;
+            children_by_depth[depth] += keys.len();
+            stack.extend(
+                keys.iter()
+                    .filter_map(|&k| Some((children.get(k)?, depth + 1))),
+            );
+        }
+        stats.fan_out_by_depth = children_by_depth
+            .iter()
+            .zip(interior_by_depth.iter())
+            .map(|(&c, &n)| if n == 0 { 0.0 } else { c as f64 / n as f64 })
+            .collect();
+        stats
+    }
+
+    /// Counts the keys less than (or equal to, when `inclusive`) `key` by
+    /// adding up the subtrees left of its path.
+    fn count_below(&self, key: &[u8], inclusive: bool) -> usize {
+        let mut count = 0;
+        let mut cur = self.root;
+        let mut depth = 0;
+        while depth < key.len() {
+            let k = key[depth];
+            // a value on the path is a proper prefix of `key`.
+            if self.nodes[cur].val.is_some() {
+                count += 1;
             }
-            loop {
-                if let Some(n) = stack.pop_back() {
-                    if let Some(children) = (*n.as_ptr()).children {
-                        if (*children.as_ptr()).head().is_none() {
-                            if let Some(temp) = stack.pop_back() {
-                                if let Some(children) = (*temp.as_ptr()).children {
-                                    (*children.as_ptr()).del((*n.as_ptr()).node_key);
-                                }
-                                stack.push_back(temp);
-                            }
-                        } else {
-                            break;
-                        }
-                    }
+            let children = match self.children(cur) {
+                Some(children) => children,
+                None => return count,
+            };
+            // one ordered pass over the children left of `k`.
+            for left in children.keys().into_iter().take_while(|&c| c < k) {
+                count += children.get(left).map_or(0, |node| self.nodes[node].count);
+            }
+            let node = match children.get(k) {
+                Some(node) => node,
+                None => return count,
+            };
+            // the edge into `node` either spells the next bytes of `key` or
+            // leaves it, with the whole subtree on one side.
+            let segment = &self.nodes[node].segment;
+            let rest = &key[depth + 1..];
+            let common = Self::common_len(segment, rest);
+            if common < segment.len() {
+                if common < rest.len() && segment[common] < rest[common] {
+                    count += self.nodes[node].count;
                 }
+                return count;
             }
+            cur = node;
+            depth += 1 + common;
+        }
+        if inclusive && self.nodes[cur].val.is_some() {
+            count += 1;
         }
-        true
+        count
     }
 
-    pub fn gt(&self, key: Vec<u8>) -> Option<T> {
-        self._gt(key, false)
+    /// Finds the node reached by following `key` from the root.
+    fn find(&self, key: &[u8]) -> Link<T, C> {
+        let mut cur = self.root;
+        let mut depth = 0;
+        while depth < key.len() {
+            let (child, len) = self.child_on(cur, &key[depth..])?;
+            cur = child;
+            depth += len;
+        }
+        Some(cur)
     }
 
-    pub fn gte(&self, key: Vec<u8>) -> Option<T> {
-        self._gt(key, false)
+    /// Finds the topmost node whose path starts with `prefix`, which may end
+    /// inside the edge into it, pushing its ancestors onto `path`.
+    fn find_prefix(&self, prefix: &[u8], path: &mut Vec<NodeId<T, C>>) -> Link<T, C> {
+        let mut cur = self.root;
+        let mut depth = 0;
+        while depth < prefix.len() {
+            let child = self.children(cur)?.get(prefix[depth])?;
+            let segment = &self.nodes[child].segment;
+            let rest = &prefix[depth + 1..];
+            path.push(cur);
+            if rest.len() < segment.len() {
+                return segment.starts_with(rest).then_some(child);
+            }
+            if !rest.starts_with(segment) {
+                return None;
+            }
+            cur = child;
+            depth += 1 + segment.len();
+        }
+        Some(cur)
     }
 
-    pub fn _gt(&self, key: Vec<u8>, eq: bool) -> Option<T> {
-        unsafe {
-            let mut cur = self.root;
-            for k in key {
-                if let Some(cur_ptr) = cur {
-                    if let Some(children) = (*cur_ptr.as_ptr()).children {
-                        let node = (*children.as_ptr()).get(k);
-                        if let Some(node_v) = node {
-                            if let Some(val) = (*node_v.as_ptr()).val.clone() {
-                                if eq {
-                                    return Some(val);
-                                } else {
-                                    if let Some(next_ptr) = (*node_v.as_ptr()).next {
-                                        return (*next_ptr.as_ptr()).val.clone();
-                                    }
-                                }
-                            }
-                        }
-                        cur = node;
-                        continue;
+    /// Puts `node` into the leaf chain right before `next`, or at the end when
+    /// `next` is `None`.
+    fn link(&mut self, node: NodeId<T, C>, next: Link<T, C>) {
+        let prev = match next {
+            Some(next) => self.nodes[next].prev,
+            None => self.tail,
+        };
+        self.nodes[node].prev = prev;
+        self.nodes[node].next = next;
+        match prev {
+            Some(prev) => self.nodes[prev].next = Some(node),
+            None => self.head = Some(node),
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = Some(node),
+            None => self.tail = Some(node),
+        }
+    }
+
+    /// Takes `node` out of the leaf chain.
+    fn unlink(&mut self, node: NodeId<T, C>) {
+        let prev = self.nodes[node].prev.take();
+        let next = self.nodes[node].next.take();
+        match prev {
+            Some(prev) => self.nodes[prev].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+    }
+
+    /// Finds the first leaf whose key is greater than (or equal to, when
+    /// `inclusive`) `key`.
+    pub(crate) fn lower_bound(&self, key: &[u8], inclusive: bool) -> Link<T, C> {
+        let mut stack = Vec::<NodeId<T, C>>::with_capacity(key.len());
+        let mut cur = self.root;
+        let mut depth = 0;
+        while depth < key.len() {
+            let k = key[depth];
+            let children = match self.children(cur) {
+                Some(children) => children,
+                None => return self.after(&stack, cur),
+            };
+            let node = match children.get(k) {
+                Some(node) => node,
+                None => {
+                    if let Some(next) = children.next(k) {
+                        return self.first(next);
                     }
-                    break;
+                    return self.after(&stack, cur);
+                }
+            };
+            let segment = &self.nodes[node].segment;
+            let rest = &key[depth + 1..];
+            let common = Self::common_len(segment, rest);
+            stack.push(cur);
+            if common < segment.len() {
+                // `key` leaves the edge into `node`: its subtree is either
+                // all greater or all less than `key`.
+                if common == rest.len() || segment[common] > rest[common] {
+                    return self.first(node);
                 }
+                return self.after(&stack, node);
             }
+            cur = node;
+            depth += 1 + common;
         }
-        return None;
+        if inclusive && self.nodes[cur].val.is_some() {
+            return Some(cur);
+        }
+        if let Some(head) = self.children(cur).and_then(|c| c.head()) {
+            return self.first(head);
+        }
+        self.after(&stack, cur)
+    }
+
+    /// Finds the last leaf whose key is less than (or equal to, when
+    /// `inclusive`) `key`.
+    fn upper_bound(&self, key: &[u8], inclusive: bool) -> Link<T, C> {
+        match self.lower_bound(key, !inclusive) {
+            Some(node) => self.nodes[node].prev,
+            None => self.tail,
+        }
+    }
+
+    /// The first leaf past the subtree of `node`, whose ancestors are `stack`.
+    fn after(&self, stack: &[NodeId<T, C>], mut node: NodeId<T, C>) -> Link<T, C> {
+        for &parent in stack.iter().rev() {
+            let node_key = self.nodes[node].node_key;
+            if let Some(next) = self.children(parent).and_then(|c| c.next(node_key)) {
+                return self.first(next);
+            }
+            node = parent;
+        }
+        None
+    }
+
+    /// The first leaf in the subtree rooted at `node`.
+    fn first(&self, mut node: NodeId<T, C>) -> Link<T, C> {
+        loop {
+            if self.nodes[node].val.is_some() {
+                return Some(node);
+            }
+            node = self.children(node)?.head()?;
+        }
+    }
+
+    /// The last leaf in the subtree rooted at `node`.
+    fn last(&self, mut node: NodeId<T, C>) -> Link<T, C> {
+        loop {
+            match self.children(node).and_then(|c| c.tail()) {
+                Some(tail) => node = tail,
+                None => return self.nodes[node].val.as_ref().map(|_| node),
+            }
+        }
+    }
+}
+
+impl<'a, T, C> Iterator for Iter<'a, T, C> {
+    type Item = (&'a [u8], &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.size == 0 {
+            return None;
+        }
+        let node = &self.nodes[self.head?];
+        self.size -= 1;
+        self.head = node.next;
+        Some((node.key.as_deref()?, node.val.as_ref()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+impl<'a, T, C> DoubleEndedIterator for Iter<'a, T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.size == 0 {
+            return None;
+        }
+        let node = &self.nodes[self.tail?];
+        self.size -= 1;
+        self.tail = node.prev;
+        Some((node.key.as_deref()?, node.val.as_ref()?))
+    }
+}
+
+impl<T, C> ExactSizeIterator for Iter<'_, T, C> {}
+
+impl<T, C> FusedIterator for Iter<'_, T, C> {}
+
+impl<'a, T, C> Iterator for IterMut<'a, T, C> {
+    type Item = (&'a [u8], &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.size == 0 {
+            return None;
+        }
+        let node = self.nodes.get_mut_ptr(self.head?)?;
+        // every node is yielded at most once, so the references never alias.
+        let node = unsafe { &mut *node };
+        self.size -= 1;
+        self.head = node.next;
+        Some((node.key.as_deref()?, node.val.as_mut()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size, Some(self.size))
+    }
+}
+
+impl<'a, T, C> DoubleEndedIterator for IterMut<'a, T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.size == 0 {
+            return None;
+        }
+        let node = self.nodes.get_mut_ptr(self.tail?)?;
+        // every node is yielded at most once, so the references never alias.
+        let node = unsafe { &mut *node };
+        self.size -= 1;
+        self.tail = node.prev;
+        Some((node.key.as_deref()?, node.val.as_mut()?))
+    }
+}
+
+impl<T, C> ExactSizeIterator for IterMut<'_, T, C> {}
+
+impl<T, C> FusedIterator for IterMut<'_, T, C> {}
+
+impl<T, C> Iterator for IntoIter<T, C> {
+    type Item = (Vec<u8>, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.list.size == 0 {
+            return None;
+        }
+        let node = &mut self.list.nodes[self.list.head?];
+        self.list.size -= 1;
+        self.list.head = node.next;
+        Some((node.key.take()?, node.val.take()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.size, Some(self.list.size))
+    }
+}
+
+impl<T, C> DoubleEndedIterator for IntoIter<T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.list.size == 0 {
+            return None;
+        }
+        let node = &mut self.list.nodes[self.list.tail?];
+        self.list.size -= 1;
+        self.list.tail = node.prev;
+        Some((node.key.take()?, node.val.take()?))
     }
 }
+
+impl<T, C> ExactSizeIterator for IntoIter<T, C> {}
+
+impl<T, C> FusedIterator for IntoIter<T, C> {}
+
+impl<T, C> IntoIterator for Trie<T, C> {
+    type Item = (Vec<u8>, T);
+    type IntoIter = IntoIter<T, C>;
+
+    fn into_iter(self) -> IntoIter<T, C> {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T, C: Container<T>> IntoIterator for &'a Trie<T, C> {
+    type Item = (&'a [u8], &'a T);
+    type IntoIter = Iter<'a, T, C>;
+
+    fn into_iter(self) -> Iter<'a, T, C> {
+        self.iter()
+    }
+}
+
+impl<'a, T, C: Container<T>> IntoIterator for &'a mut Trie<T, C> {
+    type Item = (&'a [u8], &'a mut T);
+    type IntoIter = IterMut<'a, T, C>;
+
+    fn into_iter(self) -> IterMut<'a, T, C> {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T, C> Iterator for Range<'a, T, C> {
+    type Item = (&'a [u8], &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = &self.nodes[self.front?];
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = node.next;
+        }
+        Some((node.key.as_deref()?, node.val.as_ref()?))
+    }
+}
+
+impl<'a, T, C> DoubleEndedIterator for Range<'a, T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = &self.nodes[self.back?];
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = node.prev;
+        }
+        Some((node.key.as_deref()?, node.val.as_ref()?))
+    }
+}
+
+impl<T, C> FusedIterator for Range<'_, T, C> {}
@@ -0,0 +1,133 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use trie::nmap::Nmap;
+use trie::trie::Trie;
+
+struct Counting;
+
+static LIVE: AtomicIsize = AtomicIsize::new(0);
+
+thread_local! {
+    // only the test thread is counted, the harness allocates concurrently.
+    static TRACKED: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if TRACKED.with(|t| t.get()) {
+            LIVE.fetch_add(1, Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if TRACKED.with(|t| t.get()) {
+            LIVE.fetch_sub(1, Ordering::SeqCst);
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn live_after<F: FnOnce()>(f: F) -> isize {
+    let before = LIVE.load(Ordering::SeqCst);
+    f();
+    LIVE.load(Ordering::SeqCst) - before
+}
+
+fn keys(n: u32) -> Vec<Vec<u8>> {
+    (0..n)
+        .map(|i| format!("{:05}", i * 7919 % 100000).into_bytes())
+        .collect()
+}
+
+#[test]
+fn no_leaks() {
+    TRACKED.with(|t| t.set(true));
+    let leaked = live_after(|| {
+        let mut trie: Trie<String> = Trie::new(5);
+        for key in keys(2000) {
+            trie.set(key.clone(), String::from_utf8(key).unwrap())
+                .unwrap();
+        }
+    });
+    assert_eq!(leaked, 0, "drop");
+
+    let leaked = live_after(|| {
+        let mut trie: Trie<String, Nmap<String>> = Trie::new(5);
+        for key in keys(2000) {
+            trie.set(key.clone(), String::from_utf8(key).unwrap())
+                .unwrap();
+        }
+        for key in keys(2000).iter().step_by(3) {
+            assert!(trie.del(key));
+        }
+        let count = trie.prefix_count(b"1");
+        assert_eq!(trie.remove_prefix(b"1"), count);
+        trie.clear();
+        for key in keys(100) {
+            trie.set(key, String::new()).unwrap();
+        }
+    });
+    assert_eq!(leaked, 0, "del, remove_prefix and clear");
+
+    let leaked = live_after(|| {
+        let mut trie: Trie<Vec<u8>> = Trie::variable();
+        for key in keys(500) {
+            trie.set(key[..(key[4] % 5) as usize].to_vec(), key)
+                .unwrap();
+        }
+        let mut iter = trie.into_iter();
+        iter.next();
+        iter.next_back();
+    });
+    assert_eq!(leaked, 0, "into_iter");
+
+    let leaked = live_after(|| {
+        let mut input: Vec<(Vec<u8>, String)> = keys(1000)
+            .into_iter()
+            .map(|k| (k, String::from("v")))
+            .collect();
+        input.sort();
+        let trie: Trie<String> = Trie::from_sorted_iter(5, input.clone()).unwrap();
+        drop(trie);
+        input.swap(10, 500);
+        assert!(Trie::<String>::from_sorted_iter(5, input).is_err());
+    });
+    assert_eq!(leaked, 0, "from_sorted_iter");
+
+    let live = LIVE.load(Ordering::SeqCst);
+    let mut trie: Trie<u32> = Trie::new(4);
+    for i in 0..1000u32 {
+        trie.set(i.to_be_bytes().to_vec(), i).unwrap();
+    }
+    for i in 0..1000u32 {
+        assert!(trie.del(i.to_be_bytes()));
+    }
+    // only the two arena buffers are left once every key is deleted.
+    assert_eq!(LIVE.load(Ordering::SeqCst) - live, 2, "del");
+
+    // removing the last child of a node that keeps its value frees the
+    // container, leaving the same slots as a trie that never had the child.
+    let mut trie: Trie<u32> = Trie::variable();
+    let mut short: Trie<u32> = Trie::variable();
+    for key in keys(1000) {
+        trie.set(key[..3].to_vec(), 0).unwrap();
+        trie.set(key[..4].to_vec(), 1).unwrap();
+        short.set(key[..3].to_vec(), 0).unwrap();
+    }
+    for key in keys(1000) {
+        trie.del(&key[..4]);
+    }
+    let (stats, want) = (trie.stats(), short.stats());
+    assert_eq!(stats.leaves, trie.len(), "reclaim");
+    assert_eq!(
+        (stats.slots, stats.empty_slots),
+        (want.slots, want.empty_slots),
+        "reclaim"
+    );
+}